@@ -1,9 +1,55 @@
 use anyhow::{Context, Result};
 
-use crate::structs::{Config, RunnerMap, RunnerStateChange};
+use crate::{
+    event::Event,
+    structs::{Config, DeliveredAlert, RunnerMap, RunnerStateChange},
+    telemetry::record_alert,
+};
 
 pub trait AlertHandler {
-    async fn send_alert(&mut self, cfg: &Config, change: RunnerStateChange) -> Result<()>;
+    async fn send_alert(&mut self, cfg: &Config, change: RunnerStateChange) -> Result<()> {
+        let event = Event::new(cfg, &change);
+        self.deliver_alert(cfg, change, &event).await
+    }
+
+    // the event is built once per change, so all outputs agree on its id and time
+    async fn deliver_alert(
+        &mut self,
+        cfg: &Config,
+        change: RunnerStateChange,
+        event: &Event,
+    ) -> Result<()>;
+}
+
+// remembers every alert the wrapped handler managed to send
+pub struct RecordingAlertHandler<H: AlertHandler> {
+    pub inner: H,
+    pub delivered: Vec<DeliveredAlert>,
+}
+
+impl<H: AlertHandler> RecordingAlertHandler<H> {
+    pub fn new(inner: H) -> Self {
+        RecordingAlertHandler {
+            inner,
+            delivered: vec![],
+        }
+    }
+}
+
+impl<H: AlertHandler> AlertHandler for RecordingAlertHandler<H> {
+    async fn deliver_alert(
+        &mut self,
+        cfg: &Config,
+        change: RunnerStateChange,
+        event: &Event,
+    ) -> Result<()> {
+        let alert = DeliveredAlert::new(&change);
+        let result = self.inner.deliver_alert(cfg, change, event).await;
+        record_alert(&alert.kind, &alert.runner_set, result.is_ok());
+        result?;
+        self.delivered.push(alert);
+        Ok(())
+    }
 }
 
 pub async fn alert_all_changes_and_update_grace_period(
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::Instrument;
+
+use crate::{
+    alert::AlertHandler,
+    event::Event,
+    github::{get_github_settings_url, runner_key},
+    notifier::{alert_span, find_runner_set, post_json},
+    structs::{Config, Notifier, Runner, RunnerSetConfig, RunnerStateChange},
+};
+
+pub const ALERT_NAME: &str = "GitHubRunnerOffline";
+
+// Posts to the Alertmanager API (`/api/v2/alerts`): offline runners fire, runners that come back
+// online or get removed resolve their alert.
+pub struct AlertmanagerAlertHandler;
+
+// https://github.com/prometheus/alertmanager/blob/main/api/v2/openapi.yaml
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertmanagerAlert {
+    // the identity of the alert; the runner fields never change for a runner key
+    pub labels: BTreeMap<&'static str, String>,
+    pub annotations: BTreeMap<&'static str, String>,
+    // Alertmanager resolves firing alerts after its resolve_timeout when this is missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<String>,
+    #[serde(rename = "generatorURL")]
+    pub generator_url: String,
+}
+
+impl AlertmanagerAlert {
+    pub fn new(runner_set: &RunnerSetConfig, runner: &Runner, resolved: bool) -> Self {
+        let labels = BTreeMap::from([
+            ("alertname", String::from(ALERT_NAME)),
+            ("runner_key", runner_key(&runner.runner_set, runner.id)),
+            ("runner_set", runner_set.slug.clone()),
+            ("runner_set_kind", String::from(runner_set.kind.as_str())),
+            ("runner", runner.name.clone()),
+            ("runner_id", runner.id.to_string()),
+            ("os", runner.os.clone()),
+        ]);
+        let state = if resolved { "is online" } else { "is offline" };
+        let annotations = BTreeMap::from([
+            (
+                "summary",
+                format!("Runner {} of {} {}", runner.name, runner_set.slug, state),
+            ),
+            ("runner_labels", runner.labels.join(", ")),
+        ]);
+        AlertmanagerAlert {
+            labels,
+            annotations,
+            ends_at: resolved.then(|| Utc::now().to_rfc3339()),
+            generator_url: get_github_settings_url(
+                &runner_set.github_base_uri,
+                runner_set.kind,
+                &runner_set.slug,
+            ),
+        }
+    }
+}
+
+impl AlertHandler for AlertmanagerAlertHandler {
+    async fn deliver_alert(
+        &mut self,
+        cfg: &Config,
+        change: RunnerStateChange,
+        _event: &Event,
+    ) -> Result<()> {
+        let resolved = match &change {
+            RunnerStateChange::Offline(_, _) => false,
+            RunnerStateChange::Created(runner) if runner.is_offline() => false,
+            RunnerStateChange::Online(_, _) | RunnerStateChange::Removed(_) => true,
+            // nothing to fire or resolve
+            RunnerStateChange::Created(_) => return Ok(()),
+        };
+        let runner_set = find_runner_set(cfg, &change)?;
+        let alert = AlertmanagerAlert::new(runner_set, change.runner(), resolved);
+        post_json(
+            cfg,
+            runner_set,
+            "Alertmanager",
+            &serde_json::to_value([alert])?,
+        )
+        .instrument(alert_span(&change))
+        .await
+    }
+}
+
+// Alertmanager expects firing alerts to be sent again before its resolve_timeout runs out.
+// Called after every scan with the runners of the scanned runner sets.
+pub async fn refresh_firing_alerts(cfg: &Config, runners: &[Runner]) -> Result<()> {
+    for runner_set in cfg
+        .runner_sets
+        .iter()
+        .filter(|s| s.notifier == Notifier::Alertmanager)
+    {
+        let mut alerts: Vec<AlertmanagerAlert> = runners
+            .iter()
+            .filter(|r| r.runner_set == runner_set.name && r.is_offline())
+            .map(|r| AlertmanagerAlert::new(runner_set, r, false))
+            .collect();
+        if alerts.is_empty() {
+            continue;
+        }
+        alerts.sort_by(|a, b| a.labels.cmp(&b.labels));
+        post_json(
+            cfg,
+            runner_set,
+            "Alertmanager",
+            &serde_json::to_value(alerts)?,
+        )
+        .await?;
+    }
+    Ok(())
+}
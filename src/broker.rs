@@ -0,0 +1,403 @@
+use anyhow::{bail, Result};
+
+#[cfg(feature = "kafka")]
+use crate::github::runner_key;
+use crate::{event::Event, event_sink::EventSinkConfig, structs::Config};
+
+// the cargo feature a message broker sink needs
+pub fn required_feature(sink: &EventSinkConfig) -> Option<&'static str> {
+    match sink {
+        EventSinkConfig::Nats { .. } => Some("nats"),
+        EventSinkConfig::Mqtt { .. } => Some("mqtt"),
+        EventSinkConfig::Kafka { .. } => Some("kafka"),
+        EventSinkConfig::Redis { .. } => Some("redis"),
+        _ => None,
+    }
+}
+
+// the brokers this build supports
+const ENABLED_FEATURES: &[&str] = &[
+    #[cfg(feature = "nats")]
+    "nats",
+    #[cfg(feature = "mqtt")]
+    "mqtt",
+    #[cfg(feature = "kafka")]
+    "kafka",
+    #[cfg(feature = "redis")]
+    "redis",
+];
+
+pub fn is_enabled(feature: &str) -> bool {
+    ENABLED_FEATURES.contains(&feature)
+}
+
+// The subject, topic or stream of an event: the event_topic of its runner set or the one of the
+// sink. {runner_set} is replaced by the slug and {kind} by the kind of the event.
+pub fn event_topic(cfg: &Config, default: &str, event: &Event) -> String {
+    let template = cfg
+        .runner_sets
+        .iter()
+        .find(|s| s.name == event.runner_set.name)
+        .and_then(|s| s.event_topic.as_deref())
+        .unwrap_or(default);
+    // the characters allowed in the names of all brokers
+    let runner_set: String = event
+        .runner_set
+        .slug
+        .as_deref()
+        .unwrap_or("unknown")
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect();
+    template
+        .replace("{runner_set}", &runner_set)
+        .replace("{kind}", event.kind.as_str())
+}
+
+// The connections are kept across scans.
+// One that fails is dropped and opened again for the next event.
+// Everything is awaited during the scan, so a broker that is down must not block it: every
+// connect and publish gives up after the inbound_timeout.
+#[cfg(any(feature = "nats", feature = "mqtt", feature = "redis"))]
+async fn with_timeout<T>(
+    timeout: std::time::Duration,
+    what: &str,
+    future: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    use anyhow::Context;
+    tokio::time::timeout(timeout, future)
+        .await
+        .with_context(|| format!("{} timed out after {:?}", what, timeout))?
+}
+
+#[cfg(feature = "nats")]
+pub mod nats {
+    use anyhow::{Context, Result};
+    use std::{collections::HashMap, sync::LazyLock, time::Duration};
+    use tokio::sync::Mutex;
+
+    use super::with_timeout;
+
+    static CLIENTS: LazyLock<Mutex<HashMap<String, async_nats::Client>>> =
+        LazyLock::new(Default::default);
+
+    pub async fn publish(url: &str, subject: String, line: &str, timeout: Duration) -> Result<()> {
+        let mut clients = CLIENTS.lock().await;
+        if !clients.contains_key(url) {
+            let connect = async {
+                async_nats::connect(url)
+                    .await
+                    .with_context(|| format!("Unable to connect to NATS at {}", url))
+            };
+            let client = with_timeout(timeout, "Connecting to NATS", connect).await?;
+            clients.insert(String::from(url), client);
+        }
+        let client = &clients[url];
+        let send = async {
+            client.publish(subject, line.to_owned().into()).await?;
+            client.flush().await?;
+            anyhow::Ok(())
+        };
+        let result = with_timeout(timeout, "Publishing to NATS", send).await;
+        if result.is_err() {
+            clients.remove(url);
+        }
+        result.context("Unable to publish event to NATS")
+    }
+
+    pub async fn retain(urls: &[&str]) {
+        CLIENTS
+            .lock()
+            .await
+            .retain(|url, _| urls.contains(&url.as_str()));
+    }
+}
+
+#[cfg(feature = "mqtt")]
+pub mod mqtt {
+    use anyhow::{anyhow, Context, Result};
+    use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+    use std::{collections::HashMap, sync::LazyLock, time::Duration};
+    use tokio::{
+        sync::{mpsc, Mutex},
+        task::JoinHandle,
+    };
+
+    use super::with_timeout;
+
+    struct Connection {
+        client: AsyncClient,
+        // a PUBACK for every delivered message, or why the connection broke
+        acks: mpsc::UnboundedReceiver<Result<u16, String>>,
+        event_loop: JoinHandle<()>,
+    }
+
+    // the event loop would otherwise keep reconnecting to a broker that isn't configured anymore
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            self.event_loop.abort();
+        }
+    }
+
+    static CONNECTIONS: LazyLock<Mutex<HashMap<String, Connection>>> =
+        LazyLock::new(Default::default);
+
+    fn connect(url: &str) -> Result<Connection> {
+        let options =
+            MqttOptions::parse_url(url).with_context(|| format!("Invalid MQTT url {}", url))?;
+        let (client, mut event_loop) = AsyncClient::new(options, 10);
+        let (ack_sender, acks) = mpsc::unbounded_channel();
+        // the event loop connects, sends the queued messages and receives the PUBACKs
+        // it ends with the connection; the next event opens a new one
+        let event_loop = tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Incoming(Packet::PubAck(ack))) => {
+                        let _ = ack_sender.send(Ok(ack.pkid));
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        let _ = ack_sender.send(Err(e.to_string()));
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Connection {
+            client,
+            acks,
+            event_loop,
+        })
+    }
+
+    // the url needs a client_id, e.g. mqtt://mosquitto:1883?client_id=gh_runner_uptime
+    // returns once the broker confirmed the message
+    pub async fn publish(url: &str, topic: String, line: &str, timeout: Duration) -> Result<()> {
+        let mut connections = CONNECTIONS.lock().await;
+        // a connection that broke while idle is opened again right away
+        if connections
+            .get(url)
+            .is_none_or(|connection| connection.event_loop.is_finished())
+        {
+            connections.insert(String::from(url), connect(url)?);
+        }
+        let connection = connections.get_mut(url).unwrap();
+        // messages are published one at a time, so the next PUBACK is the one of this message
+        let send = async {
+            connection
+                .client
+                .try_publish(topic, QoS::AtLeastOnce, false, line.as_bytes())?;
+            match connection.acks.recv().await {
+                Some(Ok(_)) => Ok(()),
+                Some(Err(e)) => Err(anyhow!("MQTT connection failed: {}", e)),
+                None => Err(anyhow!("MQTT connection closed")),
+            }
+        };
+        let result = with_timeout(timeout, "Publishing to MQTT", send).await;
+        if result.is_err() {
+            connections.remove(url);
+        }
+        result.context("Unable to publish event to MQTT")
+    }
+
+    pub async fn retain(urls: &[&str]) {
+        CONNECTIONS
+            .lock()
+            .await
+            .retain(|url, _| urls.contains(&url.as_str()));
+    }
+}
+
+#[cfg(feature = "kafka")]
+pub mod kafka {
+    use anyhow::{anyhow, Context, Result};
+    use rdkafka::{
+        producer::{FutureProducer, FutureRecord},
+        ClientConfig,
+    };
+    use std::{collections::HashMap, sync::LazyLock, time::Duration};
+    use tokio::sync::Mutex;
+
+    static PRODUCERS: LazyLock<Mutex<HashMap<String, FutureProducer>>> =
+        LazyLock::new(Default::default);
+
+    // the key keeps the events of a runner in order
+    pub async fn publish(
+        brokers: &str,
+        topic: String,
+        key: &str,
+        line: &str,
+        timeout: Duration,
+    ) -> Result<()> {
+        let producer = {
+            let mut producers = PRODUCERS.lock().await;
+            if !producers.contains_key(brokers) {
+                // creating the producer doesn't connect yet
+                let producer: FutureProducer = ClientConfig::new()
+                    .set("bootstrap.servers", brokers)
+                    .set("message.timeout.ms", timeout.as_millis().to_string())
+                    .create()
+                    .with_context(|| format!("Unable to connect to Kafka at {}", brokers))?;
+                producers.insert(String::from(brokers), producer);
+            }
+            producers[brokers].clone()
+        };
+        // librdkafka reconnects on its own; message.timeout.ms bounds the delivery
+        producer
+            .send(FutureRecord::to(&topic).key(key).payload(line), timeout)
+            .await
+            .map_err(|(e, _)| anyhow!(e))
+            .context("Unable to publish event to Kafka")?;
+        Ok(())
+    }
+
+    pub async fn retain(brokers: &[&str]) {
+        PRODUCERS
+            .lock()
+            .await
+            .retain(|b, _| brokers.contains(&b.as_str()));
+    }
+}
+
+#[cfg(feature = "redis")]
+pub mod redis_streams {
+    use anyhow::{Context, Result};
+    use redis::{aio::MultiplexedConnection, AsyncCommands};
+    use std::{collections::HashMap, sync::LazyLock, time::Duration};
+    use tokio::sync::Mutex;
+
+    use super::with_timeout;
+
+    static CONNECTIONS: LazyLock<Mutex<HashMap<String, MultiplexedConnection>>> =
+        LazyLock::new(Default::default);
+
+    // every event is an entry with a single field "event"
+    pub async fn publish(url: &str, stream: String, line: &str, timeout: Duration) -> Result<()> {
+        let mut connections = CONNECTIONS.lock().await;
+        if !connections.contains_key(url) {
+            let client = redis::Client::open(url)?;
+            let connect = async {
+                client
+                    .get_multiplexed_async_connection()
+                    .await
+                    .with_context(|| format!("Unable to connect to Redis at {}", url))
+            };
+            let connection = with_timeout(timeout, "Connecting to Redis", connect).await?;
+            connections.insert(String::from(url), connection);
+        }
+        let connection = connections.get_mut(url).unwrap();
+        let send = async {
+            let _: String = connection.xadd(&stream, "*", &[("event", line)]).await?;
+            anyhow::Ok(())
+        };
+        let result = with_timeout(timeout, "Publishing to Redis", send).await;
+        if result.is_err() {
+            connections.remove(url);
+        }
+        result.context("Unable to publish event to Redis")
+    }
+
+    pub async fn retain(urls: &[&str]) {
+        CONNECTIONS
+            .lock()
+            .await
+            .retain(|url, _| urls.contains(&url.as_str()));
+    }
+}
+
+// Closes the connections to the brokers that aren't configured anymore, after a reload.
+// sinks is unused when gh_runner_uptime is built without any broker
+#[allow(unused_variables)]
+pub async fn close_unused(sinks: &[EventSinkConfig]) {
+    #[cfg(feature = "nats")]
+    {
+        let urls: Vec<&str> = sinks
+            .iter()
+            .filter_map(|sink| match sink {
+                EventSinkConfig::Nats { url, .. } => Some(url.as_str()),
+                _ => None,
+            })
+            .collect();
+        nats::retain(&urls).await;
+    }
+    #[cfg(feature = "mqtt")]
+    {
+        let urls: Vec<&str> = sinks
+            .iter()
+            .filter_map(|sink| match sink {
+                EventSinkConfig::Mqtt { url, .. } => Some(url.as_str()),
+                _ => None,
+            })
+            .collect();
+        mqtt::retain(&urls).await;
+    }
+    #[cfg(feature = "kafka")]
+    {
+        let brokers: Vec<&str> = sinks
+            .iter()
+            .filter_map(|sink| match sink {
+                EventSinkConfig::Kafka { brokers, .. } => Some(brokers.as_str()),
+                _ => None,
+            })
+            .collect();
+        kafka::retain(&brokers).await;
+    }
+    #[cfg(feature = "redis")]
+    {
+        let urls: Vec<&str> = sinks
+            .iter()
+            .filter_map(|sink| match sink {
+                EventSinkConfig::Redis { url, .. } => Some(url.as_str()),
+                _ => None,
+            })
+            .collect();
+        redis_streams::retain(&urls).await;
+    }
+}
+
+// publishes to a message broker sink
+// line is unused when gh_runner_uptime is built without any broker
+#[allow(unused_variables)]
+pub async fn publish(
+    cfg: &Config,
+    sink: &EventSinkConfig,
+    event: &Event,
+    line: &str,
+) -> Result<()> {
+    let topic = match sink {
+        EventSinkConfig::Nats { subject, .. } => subject,
+        EventSinkConfig::Mqtt { topic, .. } | EventSinkConfig::Kafka { topic, .. } => topic,
+        EventSinkConfig::Redis { stream, .. } => stream,
+        _ => bail!("{:?} is no message broker", sink),
+    };
+    let topic = event_topic(cfg, topic, event);
+    match sink {
+        #[cfg(feature = "nats")]
+        EventSinkConfig::Nats { url, .. } => {
+            nats::publish(url, topic, line, cfg.inbound_timeout).await
+        }
+        #[cfg(feature = "mqtt")]
+        EventSinkConfig::Mqtt { url, .. } => {
+            mqtt::publish(url, topic, line, cfg.inbound_timeout).await
+        }
+        #[cfg(feature = "kafka")]
+        EventSinkConfig::Kafka { brokers, .. } => {
+            let key = runner_key(&event.runner_set.name, event.runner.id);
+            kafka::publish(brokers, topic, &key, line, cfg.inbound_timeout).await
+        }
+        #[cfg(feature = "redis")]
+        EventSinkConfig::Redis { url, .. } => {
+            redis_streams::publish(url, topic, line, cfg.inbound_timeout).await
+        }
+        // the config is rejected when the feature is missing
+        _ => bail!(
+            "gh_runner_uptime has been built without the {} feature",
+            required_feature(sink).unwrap_or_default()
+        ),
+    }
+}
@@ -0,0 +1,158 @@
+use reqwest::Url;
+use std::collections::HashMap;
+
+use crate::{
+    github::get_runners_for_sets,
+    inbound_alert_handler::InboundAlertHandler,
+    report::{
+        FlappingRunner, LifecycleEvent, OfflineRunner, Outage, ReportRunner, RunnerSetReport,
+    },
+    structs::{Config, Runner, RunnerStateChange},
+    template::REPORT,
+};
+
+pub fn sample_runner(runner_set: &str, online: bool) -> Runner {
+    Runner {
+        utc_ping_time: String::from("2024-05-01T00:00:00+00:00"),
+        interpret_online: Some(online),
+        online_for_github_api: online,
+        busy: false,
+        online_state_change_since: 0,
+        runner_set: String::from(runner_set),
+        id: 42,
+        name: String::from("sample-runner"),
+        os: String::from("linux"),
+        labels: vec![String::from("self-hosted"), String::from("linux")],
+        webhook_endpoint: String::new(),
+        webhook_cloudevents: None,
+    }
+}
+
+// every list contains one element so loops in the template get rendered as well
+pub fn sample_report(runner_set: &str) -> RunnerSetReport {
+    let runner = ReportRunner {
+        name: String::from("sample-runner"),
+        id: 42,
+    };
+    let time = String::from("2024-05-01T00:00:00+00:00");
+    let event = LifecycleEvent {
+        runner: runner.clone(),
+        utc_time: time.clone(),
+    };
+    RunnerSetReport {
+        runner_set: String::from(runner_set),
+        from: time.clone(),
+        to: String::from("2024-05-02T00:00:00+00:00"),
+        runner_count: 1,
+        offline_runners: vec![OfflineRunner {
+            runner: runner.clone(),
+            offline_since: Some(time.clone()),
+        }],
+        longest_outages: vec![Outage {
+            runner: runner.clone(),
+            start: time,
+            end: None,
+            duration_seconds: 3600,
+            duration: String::from("1h 0m"),
+        }],
+        flappiest_runners: vec![FlappingRunner {
+            runner,
+            state_changes: 2,
+        }],
+        created_runners: vec![event.clone()],
+        removed_runners: vec![event],
+    }
+}
+
+// Everything check-config verifies beyond parsing the config, without contacting GitHub.
+// Returns a description of every problem found.
+pub fn validate_cfg(cfg: &Config) -> Vec<String> {
+    let mut problems = vec![];
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for runner_set in &cfg.runner_sets {
+        *counts.entry(&runner_set.name).or_default() += 1;
+    }
+    for runner_set in &cfg.runner_sets {
+        if counts
+            .remove(runner_set.name.as_str())
+            .is_some_and(|c| c > 1)
+        {
+            problems.push(format!("{} is configured more than once", runner_set.name));
+        }
+        // the endpoint itself isn't printed, it usually contains a secret
+        match Url::parse(&runner_set.webhook_endpoint) {
+            Err(e) => problems.push(format!(
+                "{}: webhook_endpoint is not a valid URL: {}",
+                runner_set.name, e
+            )),
+            Ok(url) if url.scheme() != "https" && !cfg.allow_http => problems.push(format!(
+                "{}: webhook_endpoint must use https",
+                runner_set.name
+            )),
+            Ok(_) => (),
+        }
+    }
+
+    let alert_handler = match InboundAlertHandler::new(cfg) {
+        Ok(alert_handler) => alert_handler,
+        Err(e) => {
+            problems.push(format!("Failed to compile the templates: {:#}", e));
+            return problems;
+        }
+    };
+    // the headers are rendered per runner set, the templates are shared
+    let mut render_problems = vec![];
+    for runner_set in &cfg.runner_sets {
+        // the other notifiers build their alerts without templates
+        if runner_set.notifier.uses_templates() {
+            let (online, offline) = (
+                sample_runner(&runner_set.name, true),
+                sample_runner(&runner_set.name, false),
+            );
+            for change in [
+                RunnerStateChange::Created(online.clone()),
+                RunnerStateChange::Removed(online.clone()),
+                RunnerStateChange::Offline(online.clone(), offline.clone()),
+                RunnerStateChange::Online(offline, online),
+            ] {
+                if let Err(e) = alert_handler.render_alert(cfg, &change) {
+                    render_problems.push(format!("{:#}", e));
+                }
+            }
+        }
+        if runner_set.notifier.sends_reports()
+            && alert_handler
+                .templates
+                .has_template(&runner_set.name, REPORT)
+        {
+            if let Err(e) =
+                alert_handler.render_report(runner_set, &sample_report(&runner_set.name))
+            {
+                render_problems.push(format!("{:#}", e));
+            }
+        }
+    }
+    for problem in render_problems {
+        if !problems.contains(&problem) {
+            problems.push(problem);
+        }
+    }
+    problems
+}
+
+// check that every PAT is able to list the runners of its runner set
+pub async fn probe_github(cfg: &Config) -> Vec<String> {
+    let mut problems = vec![];
+    for runner_set in &cfg.runner_sets {
+        match get_runners_for_sets(&[runner_set], true).await {
+            Ok(runners) => println!(
+                "{}: GitHub access OK; {} runners",
+                runner_set.name,
+                runners.len()
+            ),
+            Err(e) => problems.push(format!("{}: {:#}", runner_set.name, e)),
+        }
+    }
+    problems
+}
@@ -0,0 +1,270 @@
+use anyhow::{ensure, Context, Result};
+use chrono::Utc;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{io::Read, path::Path, sync::Arc};
+use tokio::sync::RwLock;
+
+use crate::{
+    check::{probe_github, validate_cfg},
+    config::parse_cfg,
+    github::get_all_runners,
+    history::{
+        append_history, compute_uptime, format_uptime_report, load_history, parse_report_window,
+        HistoryEntry,
+    },
+    logging::LogFormat,
+    redact::redact,
+    scan::Scanner,
+    signature::{verify, DEFAULT_TOLERANCE_SECONDS},
+    structs::{Config, MonitorState, Runner, RunnerMap},
+};
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Monitors GitHub Actions runners and alerts on state changes"
+)]
+pub struct Cli {
+    #[arg(long, global = true, default_value = "./config.yaml")]
+    pub config: String,
+    /// e.g. `debug` or `info,gh_runner_uptime=debug`; overrides log_level from the config
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+    /// overrides log_format from the config
+    #[arg(long, global = true, value_enum)]
+    pub log_format: Option<LogFormat>,
+    // runs the daemon when no subcommand is given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Monitor the runners until the process is stopped; scans are triggered with SIGHUP
+    Run {
+        /// Print the alerts and reports instead of sending them to the webhooks
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Scan all runner sets once, send the alerts and print them
+    ScanOnce {
+        /// Where the runner state is kept between runs; the first run only initializes it
+        #[arg(long, default_value = "./runner_state.json")]
+        state: String,
+    },
+    /// Validate the configuration, the templates and the webhook URLs
+    CheckConfig {
+        /// Also check that every GitHub PAT is able to list its runners
+        #[arg(long)]
+        probe_github: bool,
+    },
+    /// Print all runners GitHub reports right now
+    ListRunners {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Print the uptime of all runners from the history file
+    UptimeReport {
+        /// RFC 3339 timestamp; defaults to 30 days before --to
+        #[arg(long)]
+        from: Option<String>,
+        /// RFC 3339 timestamp; defaults to now
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Verify the signature of a webhook request; the body is read from stdin
+    VerifySignature {
+        /// File containing the webhook_signing_secret
+        #[arg(long)]
+        secret_file: String,
+        /// The X-Signature-Timestamp header
+        #[arg(long)]
+        timestamp: i64,
+        /// The X-Signature-256 header
+        #[arg(long)]
+        signature: String,
+        /// How old the request may be
+        #[arg(long, default_value_t = DEFAULT_TOLERANCE_SECONDS)]
+        tolerance_seconds: i64,
+    },
+    /// Print the JSON Schema of the events written to the event sinks and given to the templates
+    EventSchema,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+pub fn save_state(path: &str, runners: &RunnerMap) -> Result<()> {
+    let json = serde_json::to_string_pretty(runners)?;
+    std::fs::write(path, json).with_context(|| format!("Unable to write state file {}", path))
+}
+
+// None if there is no state file yet
+// runners of runner sets that aren't configured anymore are dropped
+pub fn load_state(path: &str, cfg: &Config) -> Result<Option<RunnerMap>> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read state file {}", path))?;
+    let mut runners: RunnerMap =
+        serde_json::from_str(&json).with_context(|| format!("Invalid state file {}", path))?;
+    runners.retain(|_, runner| {
+        match cfg.runner_sets.iter().find(|s| s.name == runner.runner_set) {
+            Some(runner_set) => {
+                // the webhook endpoint is never written to the state file
+                runner.webhook_endpoint = runner_set.webhook_endpoint.clone();
+                runner.webhook_cloudevents = runner_set.webhook.cloudevents;
+                true
+            }
+            None => false,
+        }
+    });
+    Ok(Some(runners))
+}
+
+// Every run compares against the state of the previous run.
+// The first run initializes the state like on startup of the daemon, without sending alerts.
+// The state is only saved when all alerts got delivered, like in the daemon.
+pub async fn scan_once(cfg_path: &str, state_path: &str) -> Result<()> {
+    let cfg = Arc::new(parse_cfg(cfg_path, false)?);
+    let Some(runners) = load_state(state_path, &cfg)? else {
+        let runners = get_all_runners(&cfg, true).await?;
+        if let Some(history_path) = &cfg.history_path {
+            let entries: Vec<HistoryEntry> = runners.values().map(HistoryEntry::observed).collect();
+            append_history(history_path, &entries)?;
+        }
+        save_state(state_path, &runners)?;
+        println!(
+            "Initialized {} with {} runners; no alerts sent",
+            state_path,
+            runners.len()
+        );
+        return Ok(());
+    };
+
+    let state = Arc::new(RwLock::new(MonitorState::default()));
+    state.write().await.runners = runners;
+    let scanner = Scanner::new(cfg, state.clone())?;
+    let report = scanner.scan(None).await?;
+    for alert in &report.alerts {
+        println!(
+            "{:<8} {} (#{}) in {}",
+            alert.kind, alert.runner_name, alert.runner_id, alert.runner_set
+        );
+    }
+    let state = state.read().await;
+    save_state(state_path, &state.runners)
+}
+
+pub async fn check_config(cfg_path: &str, probe: bool) -> Result<()> {
+    let cfg = parse_cfg(cfg_path, false)?;
+    let mut problems = validate_cfg(&cfg);
+    if probe {
+        problems.extend(probe_github(&cfg).await);
+    }
+    for problem in &problems {
+        eprintln!("Problem: {}", redact(problem));
+    }
+    ensure!(
+        problems.is_empty(),
+        "{} problems found in {}",
+        problems.len(),
+        cfg_path
+    );
+    println!(
+        "Configuration OK; {} runner sets configured",
+        cfg.runner_sets.len()
+    );
+    Ok(())
+}
+
+pub fn format_runner_table(runners: &[&Runner]) -> String {
+    let header = ["RUNNER SET", "ID", "NAME", "STATUS", "BUSY", "OS", "LABELS"];
+    let rows: Vec<[String; 7]> = runners
+        .iter()
+        .map(|r| {
+            [
+                r.runner_set.clone(),
+                r.id.to_string(),
+                r.name.clone(),
+                String::from(if r.online_for_github_api {
+                    "online"
+                } else {
+                    "offline"
+                }),
+                String::from(if r.busy { "yes" } else { "no" }),
+                r.os.clone(),
+                r.labels.join(","),
+            ]
+        })
+        .collect();
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut out = String::new();
+    for row in std::iter::once(header.map(String::from)).chain(rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+pub async fn list_runners(cfg_path: &str, format: OutputFormat) -> Result<()> {
+    let cfg = parse_cfg(cfg_path, false)?;
+    let runners = get_all_runners(&cfg, true).await?;
+    let mut runners: Vec<&Runner> = runners.values().collect();
+    runners.sort_by(|a, b| (&a.runner_set, &a.name, a.id).cmp(&(&b.runner_set, &b.name, b.id)));
+    match format {
+        OutputFormat::Table => print!("{}", format_runner_table(&runners)),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&runners)?),
+    }
+    Ok(())
+}
+
+pub fn uptime_report(cfg_path: &str, from: Option<&str>, to: Option<&str>) -> Result<()> {
+    let cfg = parse_cfg(cfg_path, false)?;
+    let history_path = cfg
+        .history_path
+        .context("history_path needs to be configured for uptime reports")?;
+    let (from, to) = parse_report_window(from, to)?;
+    let report = compute_uptime(&load_history(&history_path)?, from, to)?;
+    print!("{}", format_uptime_report(&report));
+    Ok(())
+}
+
+// for receivers written in anything but Rust
+pub fn verify_signature(
+    secret_file: &str,
+    timestamp: i64,
+    signature: &str,
+    tolerance_seconds: i64,
+) -> Result<()> {
+    let secret = std::fs::read_to_string(secret_file)
+        .with_context(|| format!("Unable to read the secret from {}", secret_file))?;
+    let mut body = vec![];
+    std::io::stdin()
+        .read_to_end(&mut body)
+        .context("Unable to read the body from stdin")?;
+    verify(
+        secret.trim_end_matches(['\r', '\n']),
+        timestamp,
+        signature,
+        &body,
+        Utc::now().timestamp(),
+        tolerance_seconds,
+    )?;
+    println!("Signature OK");
+    Ok(())
+}
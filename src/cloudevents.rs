@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    event::{Event, EventKind},
+    github::get_github_settings_url,
+    structs::{RunnerSetConfig, WebhookOptions},
+};
+
+pub const SPEC_VERSION: &str = "1.0";
+pub const STRUCTURED_CONTENT_TYPE: &str = "application/cloudevents+json; charset=utf-8";
+
+// how the alerts are wrapped in a CloudEvent, see the HTTP protocol binding of CloudEvents 1.0
+#[derive(Debug, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CloudEventsMode {
+    // the whole event as JSON body, the rendered alert is its data
+    Structured,
+    // the attributes as ce-* headers, the rendered alert is the body
+    Binary,
+}
+
+// e.g. com.github.runner.offline
+pub fn event_type(kind: EventKind) -> String {
+    format!("com.github.runner.{}", kind.as_str())
+}
+
+// The runner set, as the URL of its runner settings on GitHub.
+// Runner sets that aren't configured anymore get a URN derived from their name instead.
+pub fn event_source(runner_set: Option<&RunnerSetConfig>, event: &Event) -> String {
+    match runner_set {
+        Some(runner_set) => get_github_settings_url(
+            &runner_set.github_base_uri,
+            runner_set.kind,
+            &runner_set.slug,
+        ),
+        None => {
+            let name = event.runner_set.name.as_bytes();
+            format!("urn:uuid:{}", Uuid::new_v5(&Uuid::NAMESPACE_OID, name))
+        }
+    }
+}
+
+// the attributes besides data and datacontenttype, in the order of the spec
+fn attributes(runner_set: Option<&RunnerSetConfig>, event: &Event) -> [(&'static str, String); 6] {
+    [
+        ("specversion", String::from(SPEC_VERSION)),
+        ("id", event.id.clone()),
+        ("source", event_source(runner_set, event)),
+        ("type", event_type(event.kind)),
+        ("subject", event.runner.name.clone()),
+        ("time", event.utc_time.clone()),
+    ]
+}
+
+// Wraps the rendered alert and returns the body to send.
+// The content type of the webhook is the datacontenttype of the event.
+pub fn wrap_alert(
+    mode: CloudEventsMode,
+    runner_set: Option<&RunnerSetConfig>,
+    event: &Event,
+    webhook: &mut WebhookOptions,
+    body: String,
+) -> Result<String> {
+    match mode {
+        CloudEventsMode::Structured => {
+            let mut envelope = serde_json::Map::new();
+            for (name, value) in attributes(runner_set, event) {
+                envelope.insert(String::from(name), Value::String(value));
+            }
+            let datacontenttype = webhook.content_type.take();
+            // JSON alerts are embedded as they are, everything else as string
+            let is_json = datacontenttype
+                .as_deref()
+                .is_some_and(|content_type| content_type.contains("json"));
+            let data = match is_json {
+                true => serde_json::from_str::<Value>(&body)
+                    .context("The alert has a JSON content type, but isn't valid JSON")?,
+                false => Value::String(body),
+            };
+            if let Some(datacontenttype) = datacontenttype {
+                envelope.insert(String::from("datacontenttype"), json!(datacontenttype));
+            }
+            envelope.insert(String::from("data"), data);
+            webhook.content_type = Some(String::from(STRUCTURED_CONTENT_TYPE));
+            Ok(serde_json::to_string(&envelope)?)
+        }
+        CloudEventsMode::Binary => {
+            for (name, value) in attributes(runner_set, event) {
+                webhook.headers.push((format!("ce-{}", name), value));
+            }
+            Ok(body)
+        }
+    }
+}
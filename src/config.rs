@@ -1,15 +1,27 @@
 use anyhow::{ensure, Context, Result};
+use reqwest::{header::HeaderName, Method};
 use serde::Deserialize;
-use serde_yaml::from_reader;
+use serde_yaml::{from_reader, from_value, Value};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::time::Duration;
+use tracing::info;
 
+use crate::broker::{is_enabled, required_feature};
+use crate::cloudevents::CloudEventsMode;
+use crate::event_sink::EventSinkConfig;
 use crate::github::{
     get_all_runners, get_github_client, get_github_enterprise_endpoint, get_github_org_endpoint,
     get_github_repo_endpoint,
 };
-use crate::structs::RunnerSetConfig;
-use crate::structs::{Config, RunnerMap};
+use crate::inbound_alert_handler::get_webhook_client;
+use crate::proxy::ProxyConfig;
+use crate::redact::{register_secret, register_url_secrets, register_webhook_secrets};
+use crate::secrets::resolve_secrets;
+use crate::structs::{Config, Notifier, RunnerMap};
+use crate::structs::{ReportInterval, RunnerSetConfig, RunnerSetKind, WebhookAuth, WebhookOptions};
+use crate::template::TemplateFormat;
+use crate::tls::TlsConfig;
 
 #[derive(Debug, Deserialize)]
 struct YAMLConfig {
@@ -25,10 +37,32 @@ struct YAMLConfig {
     #[serde(default = "default_timeout_millis")]
     pub inbound_timeout_millis: u64,
 
-    pub created_template_path: String,
-    pub removed_template_path: String,
-    pub online_template_path: String,
-    pub offline_template_path: String,
+    // optional when the template is found in the template_dir
+    pub created_template_path: Option<String>,
+    pub removed_template_path: Option<String>,
+    pub online_template_path: Option<String>,
+    pub offline_template_path: Option<String>,
+    // `<slug>/<kind>.j2` and `<kind>.j2` templates, e.g. `my-org/offline.j2` and `offline.j2`
+    pub template_dir: Option<String>,
+    // built-in templates, e.g. slack; used when no other template is found
+    pub format: Option<TemplateFormat>,
+    // old_runner, new_runner and their _json next to event in the alert templates
+    #[serde(default = "default_legacy_template_fields")]
+    pub legacy_template_fields: bool,
+
+    // append-only file with all state transitions, needed for uptime reports
+    pub history_path: Option<String>,
+    // stdout, file, unix_socket or syslog; every delivered alert is written to each as an event
+    #[serde(default)]
+    pub event_sinks: Vec<EventSinkConfig>,
+    // daily or weekly; no reports are sent when this is missing
+    pub report_interval: Option<ReportInterval>,
+    // template for the report, needed when report_interval is set
+    pub report_template_path: Option<String>,
+    // e.g. 0.0.0.0:8080; the status api is disabled when this is missing
+    pub status_api_address: Option<String>,
+    // bearer token needed for POST /scan; scanning via the api is disabled when this is missing
+    pub scan_api_token: Option<String>,
 }
 #[derive(Debug, Deserialize)]
 struct RunnerSetYAMLConfig {
@@ -38,65 +72,271 @@ struct RunnerSetYAMLConfig {
     pub github_pat: String,
     // when using the inbound_parser, the access token should be added here
     pub webhook_endpoint: String,
+    // overrides the global format
+    pub format: Option<TemplateFormat>,
+    // webhook, discord, mattermost, alertmanager or opsgenie
+    #[serde(default)]
+    pub notifier: Notifier,
+    // overrides the subject, topic or stream of the message broker event sinks
+    pub event_topic: Option<String>,
+    // POST by default
+    pub webhook_method: Option<String>,
+    // the content type of the format, if any, is sent when this is missing
+    pub webhook_content_type: Option<String>,
+    // the values are templates with the same context as the message
+    #[serde(default)]
+    pub webhook_headers: BTreeMap<String, String>,
+    // at most one of these
+    pub webhook_bearer_token: Option<String>,
+    pub webhook_basic_auth: Option<BasicAuthYAMLConfig>,
+    // needed by the opsgenie notifier
+    pub opsgenie_api_key: Option<String>,
+    // sign every request with this shared secret (see README.md)
+    pub webhook_signing_secret: Option<String>,
+    // structured or binary; wraps every alert in a CloudEvent (see README.md)
+    pub webhook_cloudevents: Option<CloudEventsMode>,
+    // private CAs, mTLS and SNI overrides (see README.md)
+    #[serde(default)]
+    pub github_tls: TlsConfig,
+    #[serde(default)]
+    pub webhook_tls: TlsConfig,
+    // the proxy settings from the environment are used when these are missing
+    pub github_proxy: Option<ProxyConfig>,
+    pub webhook_proxy: Option<ProxyConfig>,
+}
+#[derive(Debug, Deserialize)]
+struct BasicAuthYAMLConfig {
+    pub username: String,
+    pub password: Option<String>,
+}
+// make sure the secrets never show up in any log
+fn register_runner_set_secrets(runner_set: &RunnerSetYAMLConfig) {
+    register_secret(&runner_set.github_pat);
+    register_webhook_secrets(&runner_set.webhook_endpoint);
+    for proxy in [&runner_set.github_proxy, &runner_set.webhook_proxy]
+        .into_iter()
+        .flatten()
+    {
+        register_url_secrets(&proxy.url);
+        if let Some(password) = &proxy.password {
+            register_secret(password);
+        }
+    }
+    for token in [
+        &runner_set.webhook_bearer_token,
+        &runner_set.opsgenie_api_key,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        register_secret(token);
+    }
+    if let Some(secret) = &runner_set.webhook_signing_secret {
+        register_secret(secret);
+    }
+    if let Some(password) = runner_set
+        .webhook_basic_auth
+        .as_ref()
+        .and_then(|auth| auth.password.as_ref())
+    {
+        register_secret(password);
+    }
+    for (name, value) in &runner_set.webhook_headers {
+        if is_sensitive_header(name) {
+            register_secret(value);
+        }
+    }
+}
+// e.g. Authorization or X-Api-Key
+fn is_sensitive_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ["auth", "token", "key", "secret", "signature", "cookie"]
+        .iter()
+        .any(|part| name.contains(part))
+}
+fn parse_webhook_options(
+    runner_set: &RunnerSetYAMLConfig,
+    format: Option<TemplateFormat>,
+) -> Result<WebhookOptions> {
+    let method = match &runner_set.webhook_method {
+        Some(method) => Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+            .with_context(|| format!("{}: invalid webhook_method {}", runner_set.name, method))?,
+        None => Method::POST,
+    };
+    for name in runner_set.webhook_headers.keys() {
+        HeaderName::from_bytes(name.as_bytes()).with_context(|| {
+            format!("{}: invalid webhook header name {}", runner_set.name, name)
+        })?;
+    }
+    let mut auths: Vec<WebhookAuth> = [
+        runner_set
+            .webhook_bearer_token
+            .clone()
+            .map(WebhookAuth::Bearer),
+        runner_set
+            .webhook_basic_auth
+            .as_ref()
+            .map(|basic| WebhookAuth::Basic {
+                username: basic.username.clone(),
+                password: basic.password.clone(),
+            }),
+        runner_set
+            .opsgenie_api_key
+            .clone()
+            .map(WebhookAuth::GenieKey),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    ensure!(
+        auths.len() <= 1,
+        "{}: only one of webhook_bearer_token, webhook_basic_auth and opsgenie_api_key may be set",
+        runner_set.name
+    );
+    ensure!(
+        runner_set.notifier != Notifier::Opsgenie || runner_set.opsgenie_api_key.is_some(),
+        "{}: the opsgenie notifier needs opsgenie_api_key",
+        runner_set.name
+    );
+    ensure!(
+        runner_set.notifier == Notifier::Webhook || runner_set.webhook_cloudevents.is_none(),
+        "{}: webhook_cloudevents needs the webhook notifier",
+        runner_set.name
+    );
+    let auth = auths.pop();
+    Ok(WebhookOptions {
+        method,
+        content_type: runner_set
+            .webhook_content_type
+            .clone()
+            .or_else(|| format.map(|format| String::from(format.content_type()))),
+        headers: runner_set
+            .webhook_headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+        auth,
+        signing_secret: runner_set.webhook_signing_secret.clone(),
+        cloudevents: runner_set.webhook_cloudevents,
+    })
+}
+// what every http client of a runner set is built with
+struct ClientSettings {
+    github_timeout: Duration,
+    inbound_timeout: Duration,
+    allow_http: bool,
 }
+// the clients are built once per config load
+fn parse_runner_set(
+    runner_set: RunnerSetYAMLConfig,
+    kind: RunnerSetKind,
+    get_github_endpoint: fn(&str, &str) -> String,
+    clients: &ClientSettings,
+    default_format: Option<TemplateFormat>,
+) -> Result<RunnerSetConfig> {
+    register_runner_set_secrets(&runner_set);
+    let format = runner_set.format.or(runner_set.notifier.format());
+    let webhook = parse_webhook_options(&runner_set, format.or(default_format))?;
+    // the name stays the same when only the TLS settings change
+    let name = format!(
+        "{}: {}; github: {}",
+        kind.as_str(),
+        runner_set.name,
+        runner_set.github_base_uri
+    );
+    let (github_client, github_base_uri) = get_github_client(
+        clients.github_timeout,
+        &runner_set.github_pat,
+        &runner_set.github_tls,
+        runner_set.github_proxy.as_ref(),
+        &runner_set.github_base_uri,
+        clients.allow_http,
+    )
+    .with_context(|| format!("{}: unable to set up the GitHub client", name))?;
+    let (webhook_client, webhook_endpoint) = get_webhook_client(
+        clients.inbound_timeout,
+        &runner_set.webhook_tls,
+        runner_set.webhook_proxy.as_ref(),
+        &runner_set.webhook_endpoint,
+        clients.allow_http,
+    )
+    .with_context(|| format!("{}: unable to set up the webhook client", name))?;
+    Ok(RunnerSetConfig {
+        github_endpoint: get_github_endpoint(&github_base_uri, &runner_set.name),
+        name,
+        kind,
+        slug: runner_set.name,
+        github_base_uri: runner_set.github_base_uri,
+        webhook_endpoint,
+        webhook,
+        format,
+        notifier: runner_set.notifier,
+        event_topic: runner_set.event_topic,
+        webhook_client,
+        github_client,
+    })
+}
+
+fn default_legacy_template_fields() -> bool {
+    true
+}
+
 fn default_timeout_millis() -> u64 {
     30000
 }
 
-pub async fn load_cfg(cfg_path: &str) -> Result<(Config, RunnerMap)> {
-    println!("Parsing configuration");
+// parse the config without contacting GitHub
+// allow_http is only used for testing
+pub fn parse_cfg(cfg_path: &str, allow_http: bool) -> Result<Config> {
+    info!("Parsing configuration");
 
     let file = File::open(cfg_path).context("Unable to open config file")?;
-    let yml_cfg: YAMLConfig =
+    let mut yml_value: Value =
         from_reader(file).with_context(|| format!("Failed to parse yaml config {}", cfg_path))?;
+    // secrets are read again on every reload
+    resolve_secrets(&mut yml_value)
+        .with_context(|| format!("Failed to resolve secrets in {}", cfg_path))?;
+    let yml_cfg: YAMLConfig = from_value(yml_value)
+        .with_context(|| format!("Failed to parse yaml config {}", cfg_path))?;
 
+    if let Some(scan_api_token) = &yml_cfg.scan_api_token {
+        register_secret(scan_api_token);
+    }
     let github_timeout = Duration::from_millis(yml_cfg.github_timeout_millis);
     let inbound_timeout = Duration::from_millis(yml_cfg.inbound_timeout_millis);
 
-    let org_runner_sets = yml_cfg
-        .orgs
-        .into_iter()
-        .map(|org| -> Result<RunnerSetConfig> {
-            Ok(RunnerSetConfig {
-                name: format!("org: {}; github: {}", org.name, org.github_base_uri),
-                github_endpoint: get_github_org_endpoint(&org.github_base_uri, &org.name),
-                webhook_endpoint: org.webhook_endpoint,
-                github_client: get_github_client(github_timeout, &org.github_pat, false)?,
-            })
-        });
-    let repo_runner_sets = yml_cfg
-        .repos
-        .into_iter()
-        .map(|repo| -> Result<RunnerSetConfig> {
-            Ok(RunnerSetConfig {
-                name: format!("repo: {}; github: {}", repo.name, repo.github_base_uri),
-                github_endpoint: get_github_repo_endpoint(&repo.github_base_uri, &repo.name),
-                webhook_endpoint: repo.webhook_endpoint,
-                github_client: get_github_client(github_timeout, &repo.github_pat, false)?,
-            })
-        });
-    let enterprise_runner_sets =
-        yml_cfg
-            .enterprises
-            .into_iter()
-            .map(|enterprise| -> Result<RunnerSetConfig> {
-                Ok(RunnerSetConfig {
-                    name: format!(
-                        "enterprise: {}; github: {}",
-                        enterprise.name, enterprise.github_base_uri
-                    ),
-                    github_endpoint: get_github_enterprise_endpoint(
-                        &enterprise.github_base_uri,
-                        &enterprise.name,
-                    ),
-                    webhook_endpoint: enterprise.webhook_endpoint,
-                    github_client: get_github_client(
-                        github_timeout,
-                        &enterprise.github_pat,
-                        false,
-                    )?,
-                })
-            });
+    let clients = ClientSettings {
+        github_timeout,
+        inbound_timeout,
+        allow_http,
+    };
+    let org_runner_sets = yml_cfg.orgs.into_iter().map(|org| {
+        parse_runner_set(
+            org,
+            RunnerSetKind::Org,
+            get_github_org_endpoint,
+            &clients,
+            yml_cfg.format,
+        )
+    });
+    let repo_runner_sets = yml_cfg.repos.into_iter().map(|repo| {
+        parse_runner_set(
+            repo,
+            RunnerSetKind::Repo,
+            get_github_repo_endpoint,
+            &clients,
+            yml_cfg.format,
+        )
+    });
+    let enterprise_runner_sets = yml_cfg.enterprises.into_iter().map(|enterprise| {
+        parse_runner_set(
+            enterprise,
+            RunnerSetKind::Enterprise,
+            get_github_enterprise_endpoint,
+            &clients,
+            yml_cfg.format,
+        )
+    });
     let runner_sets = org_runner_sets
         .chain(repo_runner_sets)
         .chain(enterprise_runner_sets)
@@ -105,8 +345,51 @@ pub async fn load_cfg(cfg_path: &str) -> Result<(Config, RunnerMap)> {
         !runner_sets.is_empty(),
         "At least one repo, org or enterprise needs to be defined."
     );
+    if yml_cfg.report_interval.is_some() {
+        ensure!(
+            yml_cfg.report_template_path.is_some()
+                || yml_cfg.template_dir.is_some()
+                || yml_cfg.format.is_some()
+                || runner_sets
+                    .iter()
+                    .all(|s| s.format.is_some() || !s.notifier.sends_reports()),
+            "report_template_path, template_dir or format needs to be set when report_interval is set."
+        );
+        ensure!(
+            yml_cfg.history_path.is_some(),
+            "history_path needs to be set when report_interval is set."
+        );
+    }
 
-    let cfg = Config {
+    for sink in &yml_cfg.event_sinks {
+        if let EventSinkConfig::File {
+            path, max_bytes, ..
+        } = sink
+        {
+            ensure!(
+                *max_bytes > 0,
+                "max_bytes of the event file {} needs to be positive",
+                path
+            );
+        }
+        match sink {
+            // the urls may contain passwords
+            EventSinkConfig::Nats { url, .. }
+            | EventSinkConfig::Mqtt { url, .. }
+            | EventSinkConfig::Redis { url, .. } => register_url_secrets(url),
+            _ => {}
+        }
+        if let Some(feature) = required_feature(sink) {
+            ensure!(
+                is_enabled(feature),
+                "the {} event sink needs gh_runner_uptime to be built with the {} feature",
+                feature,
+                feature
+            );
+        }
+    }
+
+    Ok(Config {
         runner_sets,
         github_timeout,
         inbound_timeout,
@@ -114,11 +397,32 @@ pub async fn load_cfg(cfg_path: &str) -> Result<(Config, RunnerMap)> {
         removed_template_path: yml_cfg.removed_template_path,
         online_template_path: yml_cfg.online_template_path,
         offline_template_path: yml_cfg.offline_template_path,
+        template_dir: yml_cfg.template_dir,
+        format: yml_cfg.format,
+        legacy_template_fields: yml_cfg.legacy_template_fields,
 
         grace_period: yml_cfg.grace_period,
-        allow_http: false,
-    };
-    println!("Attempting GitHub connections");
+        allow_http,
+        dry_run: false,
+        status_api_address: yml_cfg
+            .status_api_address
+            .map(|address| {
+                address
+                    .parse()
+                    .with_context(|| format!("Invalid status_api_address {}", address))
+            })
+            .transpose()?,
+        scan_api_token: yml_cfg.scan_api_token,
+        history_path: yml_cfg.history_path,
+        event_sinks: yml_cfg.event_sinks,
+        report_interval: yml_cfg.report_interval,
+        report_template_path: yml_cfg.report_template_path,
+    })
+}
+
+pub async fn load_cfg(cfg_path: &str) -> Result<(Config, RunnerMap)> {
+    let cfg = parse_cfg(cfg_path, false)?;
+    info!("Attempting GitHub connections");
     let runners = get_all_runners(&cfg, true).await?;
     Ok((cfg, runners))
 }
@@ -0,0 +1,152 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tera::{Context, Tera};
+
+use crate::{
+    status_api::StatusApiState,
+    structs::{DeliveredAlert, MonitorState},
+};
+
+const DASHBOARD_TEMPLATE: &str = include_str!("templates/dashboard.html.j2");
+const REFRESH_SECONDS: u32 = 30;
+
+#[derive(Debug, Deserialize)]
+pub struct DashboardQuery {
+    // empty strings come from the "all" option of the filter form
+    set: Option<String>,
+    label: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DashboardRunner {
+    name: String,
+    id: i64,
+    online: bool,
+    online_for_github_api: bool,
+    online_state_change_since: u32,
+    busy: bool,
+    in_state_for: String,
+    os: String,
+    labels: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DashboardRunnerSet {
+    name: String,
+    last_scan_time: Option<String>,
+    runners: Vec<DashboardRunner>,
+}
+
+// e.g. 3d 4h, 2h 5m or 42s
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.num_seconds().max(0);
+    let (days, hours, mins) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, mins)
+    } else if mins > 0 {
+        format!("{}m {}s", mins, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+pub fn render_dashboard(
+    runner_set_names: &[String],
+    state: &MonitorState,
+    selected_set: Option<&str>,
+    selected_label: Option<&str>,
+) -> tera::Result<String> {
+    let now = Utc::now();
+    let all_labels: BTreeSet<&String> = state.runners.values().flat_map(|r| &r.labels).collect();
+
+    let sets: Vec<DashboardRunnerSet> = runner_set_names
+        .iter()
+        .filter(|name| selected_set.is_none_or(|s| s == name.as_str()))
+        .map(|name| {
+            let mut runners: Vec<DashboardRunner> = state
+                .runners
+                .iter()
+                .filter(|(_, r)| &r.runner_set == name)
+                .filter(|(_, r)| selected_label.is_none_or(|l| r.labels.iter().any(|rl| rl == l)))
+                .map(|(key, r)| DashboardRunner {
+                    name: r.name.clone(),
+                    id: r.id,
+                    online: r.interpret_online.unwrap_or(r.online_for_github_api),
+                    online_for_github_api: r.online_for_github_api,
+                    online_state_change_since: r.online_state_change_since,
+                    busy: r.busy,
+                    in_state_for: state
+                        .state_since
+                        .get(key)
+                        .map_or(String::from("unknown"), |since| {
+                            format_duration(now - *since)
+                        }),
+                    os: r.os.clone(),
+                    labels: r.labels.clone(),
+                })
+                .collect();
+            runners.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+            DashboardRunnerSet {
+                name: name.clone(),
+                last_scan_time: state
+                    .runner_sets
+                    .get(name)
+                    .and_then(|s| s.last_scan_time.clone()),
+                runners,
+            }
+        })
+        .collect();
+    let recent_alerts: Vec<&DeliveredAlert> = state
+        .recent_alerts
+        .iter()
+        .rev()
+        .filter(|a| selected_set.is_none_or(|s| s == a.runner_set))
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("refresh_seconds", &REFRESH_SECONDS);
+    ctx.insert("generated_at", &now.to_rfc3339());
+    ctx.insert("all_sets", runner_set_names);
+    ctx.insert("all_labels", &all_labels);
+    ctx.insert("selected_set", &selected_set.unwrap_or(""));
+    ctx.insert("selected_label", &selected_label.unwrap_or(""));
+    ctx.insert("sets", &sets);
+    ctx.insert("recent_alerts", &recent_alerts);
+    // the dashboard is small, compiling it for every request is fine
+    Tera::one_off(DASHBOARD_TEMPLATE, &ctx, true)
+}
+
+pub async fn get_dashboard(
+    State(api_state): State<StatusApiState>,
+    Query(query): Query<DashboardQuery>,
+) -> Response {
+    let runner_set_names: Vec<String> = api_state
+        .scanner
+        .cfg()
+        .runner_sets
+        .iter()
+        .map(|s| s.name.clone())
+        .collect();
+    let state = api_state.scanner.state.read().await;
+    match render_dashboard(
+        &runner_set_names,
+        &state,
+        query.set.as_deref().filter(|s| !s.is_empty()),
+        query.label.as_deref().filter(|l| !l.is_empty()),
+    ) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to render dashboard: {:#}", e),
+        )
+            .into_response(),
+    }
+}
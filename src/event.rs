@@ -0,0 +1,159 @@
+use chrono::Utc;
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    github::runner_key,
+    structs::{Config, Runner, RunnerStateChange},
+    template::MONITOR_INFO,
+};
+
+// Raise this when a field is renamed or removed, or its meaning changes.
+// Adding fields doesn't change the version.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+// The doc comments of the event types are the descriptions in the JSON Schema, see
+// `gh_runner_uptime event-schema` and event.schema.json.
+
+/// A runner state change as written to the event sinks and given to the templates as `event`.
+/// Unlike the other template fields, this doesn't follow changes to the internal structs.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, JsonSchema)]
+#[schemars(title = "gh_runner_uptime event")]
+pub struct Event {
+    /// Raised when a field is renamed, removed or changes its meaning; not when fields are added.
+    pub schema_version: u32,
+    /// Derived from the change; the same for the templates and all event sinks.
+    pub id: String,
+    pub kind: EventKind,
+    /// When the monitor noticed the change, as RFC 3339 timestamp.
+    pub utc_time: String,
+    pub runner_set: EventRunnerSet,
+    /// The most recent known version of the runner.
+    pub runner: RunnerSnapshot,
+    /// The runner before the change; only set for online and offline events.
+    pub previous_runner: Option<RunnerSnapshot>,
+    pub monitor: EventMonitor,
+}
+
+/// What happened to the runner.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Created,
+    Removed,
+    Online,
+    Offline,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Created => "created",
+            EventKind::Removed => "removed",
+            EventKind::Online => "online",
+            EventKind::Offline => "offline",
+        }
+    }
+}
+
+/// The runner set the runner belongs to.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, JsonSchema)]
+pub struct EventRunnerSet {
+    /// Unique for every runner set, e.g. `org: my-org; github: https://api.github.com`.
+    pub name: String,
+    /// org, repo or enterprise; null when the runner set isn't configured anymore.
+    pub kind: Option<String>,
+    /// e.g. `my-org` or `my-org/my-repo`; null when the runner set isn't configured anymore.
+    pub slug: Option<String>,
+}
+
+/// A runner at one point in time.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, JsonSchema)]
+pub struct RunnerSnapshot {
+    /// The id GitHub gave the runner; unique within its runner set.
+    pub id: i64,
+    pub name: String,
+    pub os: String,
+    pub labels: Vec<String>,
+    /// The online state after the grace period.
+    pub online: Option<bool>,
+    /// Whether the runner is executing a job.
+    pub busy: bool,
+    /// When GitHub was asked about the runner, as RFC 3339 timestamp.
+    pub utc_ping_time: String,
+}
+
+/// The gh_runner_uptime instance that noticed the change.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, JsonSchema)]
+pub struct EventMonitor {
+    pub version: String,
+    pub hostname: String,
+}
+
+impl RunnerSnapshot {
+    pub fn new(runner: &Runner) -> Self {
+        RunnerSnapshot {
+            id: runner.id,
+            name: runner.name.clone(),
+            os: runner.os.clone(),
+            labels: runner.labels.clone(),
+            online: runner.interpret_online,
+            busy: runner.busy,
+            utc_ping_time: runner.utc_ping_time.clone(),
+        }
+    }
+}
+
+impl Event {
+    pub fn new(cfg: &Config, change: &RunnerStateChange) -> Self {
+        let runner = change.runner();
+        let runner_set = cfg.runner_sets.iter().find(|s| s.name == runner.runner_set);
+        let (kind, previous_runner) = match change {
+            RunnerStateChange::Created(_) => (EventKind::Created, None),
+            RunnerStateChange::Removed(_) => (EventKind::Removed, None),
+            RunnerStateChange::Online(old_runner, _) => {
+                (EventKind::Online, Some(RunnerSnapshot::new(old_runner)))
+            }
+            RunnerStateChange::Offline(old_runner, _) => {
+                (EventKind::Offline, Some(RunnerSnapshot::new(old_runner)))
+            }
+        };
+        Event {
+            schema_version: EVENT_SCHEMA_VERSION,
+            id: event_id(change),
+            kind,
+            utc_time: Utc::now().to_rfc3339(),
+            runner_set: EventRunnerSet {
+                name: runner.runner_set.clone(),
+                kind: runner_set.map(|s| String::from(s.kind.as_str())),
+                slug: runner_set.map(|s| s.slug.clone()),
+            },
+            runner: RunnerSnapshot::new(runner),
+            previous_runner,
+            monitor: EventMonitor {
+                version: String::from(MONITOR_INFO.version),
+                hostname: MONITOR_INFO.hostname.clone(),
+            },
+        }
+    }
+}
+
+// Derived from the change, so the templates and the event sinks get the same id.
+// The ping time differs between scans.
+fn event_id(change: &RunnerStateChange) -> String {
+    let runner = change.runner();
+    let name = format!(
+        "{}; kind: {}; ping: {}",
+        runner_key(&runner.runner_set, runner.id),
+        change.kind(),
+        runner.utc_ping_time
+    );
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()).to_string()
+}
+
+pub fn event_schema() -> String {
+    let mut schema = serde_json::to_string_pretty(&schema_for!(Event)).unwrap_or_default();
+    schema.push('\n');
+    schema
+}
@@ -0,0 +1,227 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::Deserialize;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{UnixDatagram, UnixStream},
+    time::timeout,
+};
+use tracing::error;
+
+use crate::{
+    alert::AlertHandler,
+    broker::publish,
+    event::{Event, EventKind},
+    notifier::USERNAME,
+    structs::{Config, RunnerStateChange},
+};
+
+// where the events are written to, one JSON object per line
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventSinkConfig {
+    Stdout,
+    // append-only; the file is rotated to <path>.1, <path>.2, ... once it grows beyond max_bytes
+    File {
+        path: String,
+        #[serde(default = "default_max_bytes")]
+        max_bytes: u64,
+        // the number of rotated files that are kept
+        #[serde(default = "default_max_files")]
+        max_files: u32,
+    },
+    // a stream socket, e.g. the socket source of a log shipper
+    UnixSocket {
+        path: String,
+    },
+    // the local syslog daemon
+    Syslog {
+        #[serde(default = "default_syslog_path")]
+        path: String,
+    },
+    // Message brokers, each needs gh_runner_uptime to be built with the feature of the same name.
+    // The subject, topic or stream may contain {runner_set} and {kind}.
+    Nats {
+        // e.g. nats://nats:4222
+        url: String,
+        subject: String,
+    },
+    Mqtt {
+        // e.g. mqtt://mosquitto:1883?client_id=gh_runner_uptime
+        url: String,
+        topic: String,
+    },
+    Kafka {
+        // e.g. kafka-1:9092,kafka-2:9092
+        brokers: String,
+        topic: String,
+    },
+    Redis {
+        // e.g. redis://redis:6379
+        url: String,
+        stream: String,
+    },
+}
+
+fn default_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_files() -> u32 {
+    5
+}
+
+fn default_syslog_path() -> String {
+    String::from("/dev/log")
+}
+
+// the daemon facility
+const SYSLOG_FACILITY: u8 = 3;
+const SYSLOG_WARNING: u8 = 4;
+const SYSLOG_INFO: u8 = 6;
+
+// Passes every alert on to the wrapped handler and writes it to all event sinks once it has been
+// delivered. Alerts that couldn't be delivered are retried with the next scan, so they are written
+// then. When a later alert of a scan fails, the next scan delivers the earlier ones again as well,
+// so the sinks may get an event more than once.
+pub struct EventSinkAlertHandler<H: AlertHandler> {
+    pub inner: H,
+}
+
+impl<H: AlertHandler> EventSinkAlertHandler<H> {
+    pub fn new(inner: H) -> Self {
+        EventSinkAlertHandler { inner }
+    }
+}
+
+impl<H: AlertHandler> AlertHandler for EventSinkAlertHandler<H> {
+    async fn deliver_alert(
+        &mut self,
+        cfg: &Config,
+        change: RunnerStateChange,
+        event: &Event,
+    ) -> Result<()> {
+        self.inner.deliver_alert(cfg, change, event).await?;
+        write_event(cfg, event).await;
+        Ok(())
+    }
+}
+
+// a broken sink must not stop the alerting
+pub async fn write_event(cfg: &Config, event: &Event) {
+    if cfg.event_sinks.is_empty() {
+        return;
+    }
+    let line = match serde_json::to_string(event) {
+        Ok(line) => line,
+        Err(e) => return error!("Unable to serialize event: {:#}", e),
+    };
+    if cfg.dry_run {
+        println!("{}", line);
+        return;
+    }
+    for sink in &cfg.event_sinks {
+        write_line(cfg, sink, event, &line)
+            .await
+            .unwrap_or_else(|e| error!("{:#}", e));
+    }
+}
+
+async fn write_line(cfg: &Config, sink: &EventSinkConfig, event: &Event, line: &str) -> Result<()> {
+    match sink {
+        EventSinkConfig::Stdout => {
+            let mut stdout = tokio::io::stdout();
+            stdout.write_all(format!("{}\n", line).as_bytes()).await?;
+            stdout.flush().await?;
+        }
+        EventSinkConfig::File {
+            path,
+            max_bytes,
+            max_files,
+        } => {
+            // the rotation renames files, so the whole append runs on the blocking pool
+            let (owned_path, line) = (path.clone(), String::from(line));
+            let (max_bytes, max_files) = (*max_bytes, *max_files);
+            tokio::task::spawn_blocking(move || {
+                append_line(&owned_path, max_bytes, max_files, &line)
+            })
+            .await
+            .context("The event file writer panicked")?
+            .with_context(|| format!("Unable to write event to {}", path))?
+        }
+        EventSinkConfig::UnixSocket { path } => {
+            let send = async {
+                let mut stream = UnixStream::connect(path).await?;
+                stream.write_all(format!("{}\n", line).as_bytes()).await?;
+                stream.shutdown().await
+            };
+            timeout(cfg.inbound_timeout, send)
+                .await
+                .with_context(|| format!("Timed out writing event to socket {}", path))?
+                .with_context(|| format!("Unable to write event to socket {}", path))?;
+        }
+        EventSinkConfig::Syslog { path } => {
+            let socket = UnixDatagram::unbound()?;
+            socket
+                .send_to(syslog_message(event.kind, line).as_bytes(), path)
+                .await
+                .with_context(|| format!("Unable to write event to syslog {}", path))?;
+        }
+        EventSinkConfig::Nats { .. }
+        | EventSinkConfig::Mqtt { .. }
+        | EventSinkConfig::Kafka { .. }
+        | EventSinkConfig::Redis { .. } => publish(cfg, sink, event, line).await?,
+    }
+    Ok(())
+}
+
+// the format of syslog(3), which every syslog daemon understands
+pub fn syslog_message(kind: EventKind, line: &str) -> String {
+    let severity = match kind {
+        EventKind::Offline => SYSLOG_WARNING,
+        _ => SYSLOG_INFO,
+    };
+    format!(
+        "<{}>{} {}[{}]: {}",
+        SYSLOG_FACILITY * 8 + severity,
+        Utc::now().format("%b %e %H:%M:%S"),
+        USERNAME,
+        std::process::id(),
+        line
+    )
+}
+
+// rotates the file first when the line doesn't fit anymore
+pub fn append_line(path: &str, max_bytes: u64, max_files: u32, line: &str) -> Result<()> {
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    };
+    if size > 0 && size + line.len() as u64 + 1 > max_bytes {
+        rotate(path, max_files)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(format!("{}\n", line).as_bytes())?;
+    Ok(())
+}
+
+// <path> becomes <path>.1, <path>.1 becomes <path>.2 and so on; the oldest file is dropped
+fn rotate(path: &str, max_files: u32) -> Result<()> {
+    if max_files == 0 {
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+    for i in (1..max_files).rev() {
+        let from = format!("{}.{}", path, i);
+        if Path::new(&from).exists() {
+            fs::rename(&from, format!("{}.{}", path, i + 1))?;
+        }
+    }
+    fs::rename(path, format!("{}.1", path))?;
+    Ok(())
+}
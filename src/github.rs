@@ -1,4 +1,4 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use chrono::Utc;
 use reqwest::{
     header::{HeaderMap, HeaderValue},
@@ -6,8 +6,11 @@ use reqwest::{
 };
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use tracing::{debug, field::Empty, info_span, Instrument};
 
-use crate::structs::{Config, Runner, RunnerMap, RunnerSetConfig};
+use crate::proxy::ProxyConfig;
+use crate::structs::{Config, Runner, RunnerMap, RunnerSetConfig, RunnerSetKind};
+use crate::tls::{build_client, TlsConfig};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct JSONRunnerSetResponse {
@@ -40,11 +43,18 @@ async fn get_runners_for_set(
     runner_set: &RunnerSetConfig,
     interpret_immediately: bool,
 ) -> Result<RunnerMap> {
+    let span = info_span!(
+        "github_request",
+        runner_set = %runner_set.name,
+        http.status_code = Empty
+    );
     let resp = runner_set
         .github_client
         .get(&runner_set.github_endpoint)
         .send()
+        .instrument(span.clone())
         .await?;
+    span.record("http.status_code", resp.status().as_u16());
     if !resp.status().is_success() {
         bail!(
             "github runner api request returned status {} for {}; return body: {}",
@@ -54,6 +64,7 @@ async fn get_runners_for_set(
         );
     }
     let json_resp = resp.json::<JSONRunnerSetResponse>().await?;
+    span.in_scope(|| debug!("GitHub returned {} runners", json_resp.runners.len()));
 
     json_resp
         .runners
@@ -79,6 +90,7 @@ fn parse_runner(
             None
         },
         online_for_github_api,
+        busy: json_runner.busy,
         // this get's overwritten in all cases in alert_all_changes_and_update_grace_period
         online_state_change_since: 0,
         runner_set: runner_set.name.clone(),
@@ -87,29 +99,52 @@ fn parse_runner(
         os: json_runner.os,
         labels: json_runner.labels.into_iter().map(|l| l.name).collect(),
         webhook_endpoint: runner_set.webhook_endpoint.clone(),
+        webhook_cloudevents: runner_set.webhook.cloudevents,
     };
     Ok(key_runner(runner))
 }
 
 // produce the key for a runner (need for the hash map)
 pub fn key_runner(runner: Runner) -> (String, Runner) {
-    (
-        format!("{}; runner id: {}", runner.runner_set, runner.id),
-        runner,
-    )
+    (runner_key(&runner.runner_set, runner.id), runner)
+}
+
+pub fn runner_key(runner_set: &str, id: i64) -> String {
+    format!("{}; runner id: {}", runner_set, id)
 }
 
 // interpret_immediately uses what GitHub provides as the runner's online state as the interpreted
 // online state
 pub async fn get_all_runners(cfg: &Config, interpret_immediately: bool) -> Result<RunnerMap> {
+    get_runners_for_sets(
+        &cfg.runner_sets.iter().collect::<Vec<_>>(),
+        interpret_immediately,
+    )
+    .await
+}
+
+// interpret_immediately uses what GitHub provides as the runner's online state as the interpreted
+// online state
+pub async fn get_runners_for_sets(
+    runner_sets: &[&RunnerSetConfig],
+    interpret_immediately: bool,
+) -> Result<RunnerMap> {
     let mut map = RunnerMap::new();
-    for runner_set in &cfg.runner_sets {
+    for runner_set in runner_sets {
         map.extend(get_runners_for_set(runner_set, interpret_immediately).await?);
     }
     Ok(map)
 }
 
-pub fn get_github_client(timeout: Duration, pat: &str, allow_http: bool) -> Result<Client> {
+// returns the client and the base URI to use with it
+pub fn get_github_client(
+    timeout: Duration,
+    pat: &str,
+    tls: &TlsConfig,
+    proxy: Option<&ProxyConfig>,
+    base_uri: &str,
+    allow_http: bool,
+) -> Result<(Client, String)> {
     let mut headers = HeaderMap::new();
     headers.insert(
         "Accept",
@@ -123,14 +158,12 @@ pub fn get_github_client(timeout: Duration, pat: &str, allow_http: bool) -> Resu
         "X-GitHub-Api-Version",
         HeaderValue::from_static("2022-11-28"),
     );
-    let client = ClientBuilder::new()
+    let builder = ClientBuilder::new()
         .https_only(!allow_http)
         .user_agent("github uptime monitor")
         .default_headers(headers)
-        .timeout(timeout)
-        .build()?;
-
-    Ok(client)
+        .timeout(timeout);
+    build_client(builder, tls, proxy, base_uri).context("Invalid github_tls or github_proxy")
 }
 // https://docs.github.com/en/enterprise-server@3.12/rest/actions/self-hosted-runners?apiVersion=2022-11-28#list-runner-applications-for-an-organization
 pub fn get_github_repo_endpoint(base_uri: &str, repo_name: &str) -> String {
@@ -141,6 +174,26 @@ pub fn get_github_org_endpoint(base_uri: &str, org_name: &str) -> String {
     format!("{}/orgs/{}/actions/runners", base_uri, org_name)
 }
 
+// where the runners are managed in the browser
+pub fn get_github_settings_url(base_uri: &str, kind: RunnerSetKind, slug: &str) -> String {
+    let base_uri = base_uri.trim_end_matches('/');
+    // GHES serves its api below /api/v3, github.com on a subdomain
+    let web_uri = match base_uri.strip_suffix("/api/v3") {
+        Some(web_uri) => web_uri.to_string(),
+        None => base_uri.replacen("://api.", "://", 1),
+    };
+    match kind {
+        RunnerSetKind::Org => format!(
+            "{}/organizations/{}/settings/actions/runners",
+            web_uri, slug
+        ),
+        RunnerSetKind::Repo => format!("{}/{}/settings/actions/runners", web_uri, slug),
+        RunnerSetKind::Enterprise => {
+            format!("{}/enterprises/{}/settings/actions/runners", web_uri, slug)
+        }
+    }
+}
+
 // https://docs.github.com/en/enterprise-server@3.12/rest/actions/self-hosted-runners?apiVersion=2022-11-28#list-self-hosted-runners-for-an-enterprise
 pub fn get_github_enterprise_endpoint(base_uri: &str, enterprise_name: &str) -> String {
     format!(
@@ -0,0 +1,255 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use crate::structs::{DeliveredAlert, Runner};
+
+// One line of the history file.
+// The history file is append-only and contains one JSON object per line.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct HistoryEntry {
+    pub utc_time: String,
+    // created, removed, online, offline, observed or unmonitored
+    // observed entries are written on startup to record the state the monitor found
+    // unmonitored entries are written when a runner set is removed from the config
+    pub kind: String,
+    pub runner_set: String,
+    pub runner_id: i64,
+    pub runner_name: String,
+    pub labels: Vec<String>,
+    // the interpreted online state after this entry
+    // None when the runner has been removed or isn't monitored anymore
+    pub online: Option<bool>,
+}
+
+impl HistoryEntry {
+    pub fn from_alert(alert: &DeliveredAlert) -> Self {
+        HistoryEntry {
+            utc_time: alert.utc_time.clone(),
+            kind: alert.kind.clone(),
+            runner_set: alert.runner_set.clone(),
+            runner_id: alert.runner_id,
+            runner_name: alert.runner_name.clone(),
+            labels: alert.labels.clone(),
+            online: alert.online,
+        }
+    }
+
+    pub fn observed(runner: &Runner) -> Self {
+        HistoryEntry {
+            utc_time: Utc::now().to_rfc3339(),
+            kind: String::from("observed"),
+            runner_set: runner.runner_set.clone(),
+            runner_id: runner.id,
+            runner_name: runner.name.clone(),
+            labels: runner.labels.clone(),
+            online: runner.interpret_online,
+        }
+    }
+
+    pub fn unmonitored(runner: &Runner) -> Self {
+        HistoryEntry {
+            kind: String::from("unmonitored"),
+            online: None,
+            ..Self::observed(runner)
+        }
+    }
+}
+
+pub fn append_history(path: &str, entries: &[HistoryEntry]) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Unable to open history file {}", path))?;
+    let mut lines = String::new();
+    for entry in entries {
+        lines.push_str(&serde_json::to_string(entry)?);
+        lines.push('\n');
+    }
+    // one write per scan keeps the lines of a scan together
+    file.write_all(lines.as_bytes())
+        .with_context(|| format!("Unable to write history file {}", path))?;
+    Ok(())
+}
+
+// appends the entries on the blocking pool, the file writes must not stall the runtime
+pub async fn write_history(path: String, entries: Vec<HistoryEntry>) -> Result<()> {
+    tokio::task::spawn_blocking(move || append_history(&path, &entries))
+        .await
+        .context("The history writer panicked")?
+}
+
+pub fn load_history(path: &str) -> Result<Vec<HistoryEntry>> {
+    if !Path::new(path).exists() {
+        return Ok(vec![]);
+    }
+    let file = File::open(path).with_context(|| format!("Unable to open history file {}", path))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.as_ref().is_ok_and(|l| l.trim().is_empty()))
+        .map(|(i, line)| {
+            serde_json::from_str(&line?)
+                .with_context(|| format!("Invalid line {} in history file {}", i + 1, path))
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone, Default)]
+pub struct Uptime {
+    pub name: String,
+    // how long the runners have been online within the window
+    pub online_seconds: i64,
+    // how long the runners have existed within the window
+    pub observed_seconds: i64,
+    // None when nothing has been observed
+    pub uptime_percent: Option<f64>,
+}
+
+impl Uptime {
+    fn add(&mut self, online_seconds: i64, observed_seconds: i64) {
+        self.online_seconds += online_seconds;
+        self.observed_seconds += observed_seconds;
+        self.uptime_percent = (self.observed_seconds > 0)
+            .then(|| self.online_seconds as f64 * 100.0 / self.observed_seconds as f64);
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct UptimeReport {
+    pub from: String,
+    pub to: String,
+    pub runners: Vec<Uptime>,
+    pub label_pools: Vec<Uptime>,
+    pub runner_sets: Vec<Uptime>,
+}
+
+type TimedEntry<'a> = (DateTime<Utc>, &'a HistoryEntry);
+
+fn parse_time(time: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(time)
+        .with_context(|| format!("Invalid timestamp {}", time))?
+        .with_timezone(&Utc))
+}
+
+// A runner keeps the state of its last entry until the next entry.
+// While the monitor isn't running the last known state is assumed.
+pub fn compute_uptime(
+    entries: &[HistoryEntry],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<UptimeReport> {
+    // (runner set, runner id) -> entries sorted by time
+    let mut timelines: BTreeMap<(String, i64), Vec<TimedEntry>> = BTreeMap::new();
+    for entry in entries {
+        timelines
+            .entry((entry.runner_set.clone(), entry.runner_id))
+            .or_default()
+            .push((parse_time(&entry.utc_time)?, entry));
+    }
+
+    let mut runners = vec![];
+    let mut label_pools: HashMap<String, Uptime> = HashMap::new();
+    let mut runner_sets: HashMap<String, Uptime> = HashMap::new();
+    for ((runner_set, _), mut timeline) in timelines {
+        timeline.sort_by_key(|(time, _)| *time);
+        let (mut online_seconds, mut observed_seconds) = (0, 0);
+        for (i, (start, entry)) in timeline.iter().enumerate() {
+            let end = timeline.get(i + 1).map_or(to, |(next, _)| *next);
+            let (start, end) = ((*start).max(from), end.min(to));
+            if end <= start {
+                continue;
+            }
+            let seconds = (end - start).num_seconds();
+            match entry.online {
+                Some(true) => {
+                    online_seconds += seconds;
+                    observed_seconds += seconds;
+                }
+                Some(false) => observed_seconds += seconds,
+                None => (),
+            }
+        }
+
+        // the most recent entry knows the current name and labels
+        let last = timeline.last().context("timelines are never empty")?.1;
+        let mut uptime = Uptime {
+            name: format!("{} #{} ({})", last.runner_name, last.runner_id, runner_set),
+            ..Default::default()
+        };
+        uptime.add(online_seconds, observed_seconds);
+        runners.push(uptime);
+        for label in &last.labels {
+            label_pools
+                .entry(label.clone())
+                .or_insert_with(|| Uptime {
+                    name: label.clone(),
+                    ..Default::default()
+                })
+                .add(online_seconds, observed_seconds);
+        }
+        runner_sets
+            .entry(runner_set.clone())
+            .or_insert_with(|| Uptime {
+                name: runner_set.clone(),
+                ..Default::default()
+            })
+            .add(online_seconds, observed_seconds);
+    }
+
+    let sorted = |map: HashMap<String, Uptime>| {
+        let mut values: Vec<Uptime> = map.into_values().collect();
+        values.sort_by(|a, b| a.name.cmp(&b.name));
+        values
+    };
+    Ok(UptimeReport {
+        from: from.to_rfc3339(),
+        to: to.to_rfc3339(),
+        runners,
+        label_pools: sorted(label_pools),
+        runner_sets: sorted(runner_sets),
+    })
+}
+
+pub fn format_uptime_report(report: &UptimeReport) -> String {
+    let mut out = format!("Uptime from {} to {}\n", report.from, report.to);
+    for (title, rows) in [
+        ("Runner sets", &report.runner_sets),
+        ("Label pools", &report.label_pools),
+        ("Runners", &report.runners),
+    ] {
+        out.push_str(&format!("\n{}:\n", title));
+        for row in rows {
+            out.push_str(&format!(
+                "{:>8}  {}\n",
+                row.uptime_percent
+                    .map_or(String::from("n/a"), |p| format!("{:.2}%", p)),
+                row.name
+            ));
+        }
+    }
+    out
+}
+
+// default to the last 30 days
+pub fn parse_report_window(
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let to = to.map(parse_time).transpose()?.unwrap_or_else(Utc::now);
+    let from = from
+        .map(parse_time)
+        .transpose()?
+        .unwrap_or(to - chrono::Duration::days(30));
+    Ok((from, to))
+}
@@ -1,46 +1,261 @@
 use anyhow::{bail, Context, Error, Result};
-use reqwest::ClientBuilder;
-use tera::Tera;
+use chrono::{DateTime, Utc};
+use reqwest::{header::CONTENT_TYPE, Client, ClientBuilder};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tracing::{debug, field::Empty, info_span, Instrument};
 
 use crate::{
     alert::AlertHandler,
-    structs::{Config, RunnerStateChange},
+    cloudevents::wrap_alert,
+    event::Event,
+    github::{get_github_settings_url, runner_key},
+    notifier::{alert_span, apply_auth},
+    proxy::ProxyConfig,
+    rate_limit::send_with_retry,
+    redact::redact,
+    report::RunnerSetReport,
+    signature::{sign, SIGNATURE_HEADER, TIMESTAMP_HEADER},
+    structs::{Config, Runner, RunnerSetConfig, RunnerSetKind, RunnerStateChange, WebhookOptions},
+    telemetry::trace_context_headers,
+    template::{Templates, MONITOR_INFO, REPORT},
+    tls::{build_client, TlsConfig},
 };
 
 pub struct InboundAlertHandler {
-    pub templates: Tera,
+    pub templates: Arc<Templates>,
+    // since when each runner is in its interpreted state, as of before the scan
+    pub state_since: HashMap<String, DateTime<Utc>>,
+    pub scan_time: DateTime<Utc>,
+    // for the runners of removed runner sets, built on first use
+    default_client: OnceLock<Client>,
 }
 
-impl InboundAlertHandler {
-    const CREATED_TEMPLATE: &'static str = "created";
-    const REMOVED_TEMPLATE: &'static str = "removed";
-    const OFFLINE_TEMPLATE: &'static str = "offline";
-    const ONLINE_TEMPLATE: &'static str = "online";
+// the runner set as seen by the templates
+#[derive(Debug, Serialize)]
+struct TemplateRunnerSet<'a> {
+    name: &'a str,
+    // unknown for runners of a runner set that has been removed from the config
+    kind: Option<RunnerSetKind>,
+    slug: Option<&'a str>,
+    base_uri: Option<&'a str>,
+    settings_url: Option<String>,
+}
+
+impl<'a> TemplateRunnerSet<'a> {
+    fn new(name: &'a str, runner_set: Option<&'a RunnerSetConfig>) -> Self {
+        TemplateRunnerSet {
+            name,
+            kind: runner_set.map(|s| s.kind),
+            slug: runner_set.map(|s| s.slug.as_str()),
+            base_uri: runner_set.map(|s| s.github_base_uri.as_str()),
+            settings_url: runner_set
+                .map(|s| get_github_settings_url(&s.github_base_uri, s.kind, &s.slug)),
+        }
+    }
+}
 
+impl InboundAlertHandler {
+    // compiles all templates; use with_templates to reuse compiled ones
     pub fn new(cfg: &Config) -> Result<Self, Error> {
-        let mut templates = Tera::default();
-        templates.add_template_file(&cfg.created_template_path, Some(Self::CREATED_TEMPLATE))?;
-        templates.add_template_file(&cfg.removed_template_path, Some(Self::REMOVED_TEMPLATE))?;
-        templates.add_template_file(&cfg.offline_template_path, Some(Self::OFFLINE_TEMPLATE))?;
-        templates.add_template_file(&cfg.online_template_path, Some(Self::ONLINE_TEMPLATE))?;
-        Ok(InboundAlertHandler { templates })
+        Ok(Self::with_templates(Arc::new(Templates::new(cfg)?)))
     }
 
-    async fn send_inbound(&self, cfg: &Config, endpoint: &str, request_body: String) -> Result<()> {
-        println!("Sending:\n{}", request_body);
-        // TODO: reuse client
-        let client = ClientBuilder::new()
-            .https_only(!cfg.allow_http)
-            .user_agent(format!("gh_runner_uptime v{}", env!("CARGO_PKG_VERSION")))
-            .timeout(cfg.inbound_timeout)
-            .build()?;
-
-        let resp = client
-            .post(endpoint)
-            .body(request_body)
-            .send()
-            .await
-            .context("webhook request failed")?;
+    pub fn with_templates(templates: Arc<Templates>) -> Self {
+        InboundAlertHandler {
+            templates,
+            state_since: HashMap::new(),
+            scan_time: Utc::now(),
+            default_client: OnceLock::new(),
+        }
+    }
+
+    // what every template gets besides the runners or the report
+    fn base_context(
+        &self,
+        kind: &str,
+        runner_set_name: &str,
+        runner_set: Option<&RunnerSetConfig>,
+    ) -> tera::Context {
+        let mut ctx = tera::Context::new();
+        ctx.insert("kind", kind);
+        ctx.insert(
+            "runner_set",
+            &TemplateRunnerSet::new(runner_set_name, runner_set),
+        );
+        ctx.insert("scan_time", &self.scan_time.to_rfc3339());
+        ctx.insert("monitor", &*MONITOR_INFO);
+        ctx
+    }
+
+    fn render_headers(
+        &self,
+        runner_set: &RunnerSetConfig,
+        ctx: &tera::Context,
+    ) -> Result<WebhookOptions> {
+        let headers = runner_set
+            .webhook
+            .headers
+            .iter()
+            .map(|(name, _)| {
+                let value = self.templates.render_header(&runner_set.name, name, ctx)?;
+                Ok((name.clone(), value))
+            })
+            .collect::<Result<_>>()?;
+        Ok(WebhookOptions {
+            headers,
+            ..runner_set.webhook.clone()
+        })
+    }
+
+    pub async fn send_report(
+        &self,
+        cfg: &Config,
+        runner_set: &RunnerSetConfig,
+        report: &RunnerSetReport,
+    ) -> Result<()> {
+        let request = self.render_report(runner_set, report)?;
+        self.send_inbound(cfg, request).await
+    }
+
+    pub fn render_report(
+        &self,
+        runner_set: &RunnerSetConfig,
+        report: &RunnerSetReport,
+    ) -> Result<WebhookRequest> {
+        let mut ctx = self.base_context("report", &runner_set.name, Some(runner_set));
+        ctx.insert("report", report);
+        let body = self.templates.render(&runner_set.name, REPORT, &ctx)?;
+        Ok(WebhookRequest {
+            endpoint: runner_set.webhook_endpoint.clone(),
+            client: runner_set.webhook_client.clone(),
+            webhook: self.render_headers(runner_set, &ctx)?,
+            body,
+        })
+    }
+
+    // the webhook options are looked up by the runner set of the runner
+    pub fn render_alert(&self, cfg: &Config, change: &RunnerStateChange) -> Result<WebhookRequest> {
+        self.render_event_alert(cfg, change, &Event::new(cfg, change))
+    }
+
+    pub fn render_event_alert(
+        &self,
+        cfg: &Config,
+        change: &RunnerStateChange,
+        event: &Event,
+    ) -> Result<WebhookRequest> {
+        let runner = change.runner();
+        let runner_set = cfg.runner_sets.iter().find(|s| s.name == runner.runner_set);
+        let mut ctx = self.base_context(change.kind(), &runner.runner_set, runner_set);
+        ctx.insert("duration_offline", &self.duration_offline(change));
+        ctx.insert("event", event);
+        if cfg.legacy_template_fields {
+            insert_legacy_runners(&mut ctx, change)?;
+        }
+        let body = self
+            .templates
+            .render(&runner.runner_set, change.kind(), &ctx)?;
+        let (client, webhook, body) = match runner_set {
+            Some(runner_set) => {
+                let mut webhook = self.render_headers(runner_set, &ctx)?;
+                let body = match webhook.cloudevents {
+                    Some(mode) => wrap_alert(mode, Some(runner_set), event, &mut webhook, body)?,
+                    None => body,
+                };
+                (runner_set.webhook_client.clone(), webhook, body)
+            }
+            // runners from a removed runner set are sent with the defaults, in their envelope
+            None => {
+                let mut webhook = WebhookOptions::default();
+                let body = match runner.webhook_cloudevents {
+                    Some(mode) => wrap_alert(mode, None, event, &mut webhook, body)?,
+                    None => body,
+                };
+                (self.default_client(cfg)?, webhook, body)
+            }
+        };
+        Ok(WebhookRequest {
+            endpoint: runner.webhook_endpoint.clone(),
+            client,
+            webhook,
+            body,
+        })
+    }
+
+    fn default_client(&self, cfg: &Config) -> Result<Client> {
+        if let Some(client) = self.default_client.get() {
+            return Ok(client.clone());
+        }
+        // the endpoint only matters with a server_name
+        let (client, _) = get_webhook_client(
+            cfg.inbound_timeout,
+            &TlsConfig::default(),
+            None,
+            "",
+            cfg.allow_http,
+        )?;
+        Ok(self.default_client.get_or_init(|| client).clone())
+    }
+
+    // in seconds, for runners that have been offline until this change
+    fn duration_offline(&self, change: &RunnerStateChange) -> Option<i64> {
+        let old_runner = match change {
+            RunnerStateChange::Created(_) => return None,
+            RunnerStateChange::Removed(old_runner)
+            | RunnerStateChange::Offline(old_runner, _)
+            | RunnerStateChange::Online(old_runner, _) => old_runner,
+        };
+        if old_runner.interpret_online != Some(false) {
+            return None;
+        }
+        self.state_since
+            .get(&runner_key(&old_runner.runner_set, old_runner.id))
+            .map(|since| (self.scan_time - *since).num_seconds())
+    }
+
+    async fn send_inbound(&self, cfg: &Config, request: WebhookRequest) -> Result<()> {
+        if cfg.dry_run {
+            // the rendered alerts are the output of a dry run
+            println!("{}", redact(&request.body));
+            return Ok(());
+        }
+        debug!("Sending:\n{}", request.body);
+
+        let span = info_span!("webhook_delivery", http.status_code = Empty);
+        let webhook = request.webhook;
+        let mut builder = request
+            .client
+            .request(webhook.method, &request.endpoint)
+            // lets the receiver continue the trace
+            .headers(span.in_scope(trace_context_headers));
+        if let Some(content_type) = &webhook.content_type {
+            builder = builder.header(CONTENT_TYPE, content_type);
+        }
+        for (name, value) in &webhook.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(secret) = &webhook.signing_secret {
+            let timestamp = Utc::now().timestamp();
+            builder = builder.header(TIMESTAMP_HEADER, timestamp).header(
+                SIGNATURE_HEADER,
+                sign(secret, timestamp, request.body.as_bytes()),
+            );
+        }
+        let resp = send_with_retry(
+            apply_auth(builder, webhook.auth.as_ref()).body(request.body),
+            cfg.inbound_timeout,
+        )
+        .instrument(span.clone())
+        .await
+        // the url contains the webhook's secret
+        .map_err(|e| e.without_url())
+        .context("webhook request failed")?;
+        span.record("http.status_code", resp.status().as_u16());
         if !resp.status().is_success() {
             bail!(
                 "webhook request returned status {}; return body: {}",
@@ -52,65 +267,69 @@ impl InboundAlertHandler {
     }
 }
 
+// The Runner structs, as the templates got them before the event existed.
+// Their fields change with the internal structs.
+fn insert_legacy_runners(ctx: &mut tera::Context, change: &RunnerStateChange) -> Result<()> {
+    let mut insert_runner = |key: &str, runner: &Runner| -> Result<()> {
+        ctx.insert(key, runner);
+        ctx.insert(
+            format!("{}_json", key),
+            &serde_json::to_string_pretty(runner)?,
+        );
+        Ok(())
+    };
+    match change {
+        RunnerStateChange::Created(new_runner) => {
+            insert_runner("new_runner", new_runner)?;
+        }
+        RunnerStateChange::Removed(old_runner) => {
+            insert_runner("old_runner", old_runner)?;
+        }
+        RunnerStateChange::Offline(old_runner, new_runner)
+        | RunnerStateChange::Online(old_runner, new_runner) => {
+            insert_runner("old_runner", old_runner)?;
+            insert_runner("new_runner", new_runner)?;
+        }
+    };
+    Ok(())
+}
+
+// a rendered alert or report, ready to be sent
+#[derive(Debug)]
+pub struct WebhookRequest {
+    pub endpoint: String,
+    // shared by all requests of a runner set
+    pub client: Client,
+    // the header values are rendered already
+    pub webhook: WebhookOptions,
+    pub body: String,
+}
+
+// returns the client and the endpoint to use with it
+pub fn get_webhook_client(
+    timeout: Duration,
+    tls: &TlsConfig,
+    proxy: Option<&ProxyConfig>,
+    endpoint: &str,
+    allow_http: bool,
+) -> Result<(Client, String)> {
+    let builder = ClientBuilder::new()
+        .https_only(!allow_http)
+        .user_agent(format!("gh_runner_uptime v{}", env!("CARGO_PKG_VERSION")))
+        .timeout(timeout);
+    build_client(builder, tls, proxy, endpoint).context("Invalid webhook_tls or webhook_proxy")
+}
+
 impl AlertHandler for InboundAlertHandler {
-    async fn send_alert(&mut self, cfg: &Config, change: RunnerStateChange) -> Result<()> {
-        let mut ctx = tera::Context::new();
-        let (endpoint, request_body) = match change {
-            RunnerStateChange::Created(new_runner) => {
-                ctx.insert("new_runner", &new_runner);
-                ctx.insert(
-                    "new_runner_json",
-                    &serde_json::to_string_pretty(&new_runner)?,
-                );
-                (
-                    new_runner.webhook_endpoint,
-                    self.templates.render(Self::CREATED_TEMPLATE, &ctx)?,
-                )
-            }
-            RunnerStateChange::Removed(old_runner) => {
-                ctx.insert("old_runner", &old_runner);
-                ctx.insert(
-                    "old_runner_json",
-                    &serde_json::to_string_pretty(&old_runner)?,
-                );
-                (
-                    old_runner.webhook_endpoint,
-                    self.templates.render(Self::REMOVED_TEMPLATE, &ctx)?,
-                )
-            }
-            RunnerStateChange::Offline(old_runner, new_runner) => {
-                ctx.insert("old_runner", &old_runner);
-                ctx.insert(
-                    "old_runner_json",
-                    &serde_json::to_string_pretty(&old_runner)?,
-                );
-                ctx.insert("new_runner", &new_runner);
-                ctx.insert(
-                    "new_runner_json",
-                    &serde_json::to_string_pretty(&new_runner)?,
-                );
-                (
-                    new_runner.webhook_endpoint,
-                    self.templates.render(Self::OFFLINE_TEMPLATE, &ctx)?,
-                )
-            }
-            RunnerStateChange::Online(old_runner, new_runner) => {
-                ctx.insert("old_runner", &old_runner);
-                ctx.insert(
-                    "old_runner_json",
-                    &serde_json::to_string_pretty(&old_runner)?,
-                );
-                ctx.insert("new_runner", &new_runner);
-                ctx.insert(
-                    "new_runner_json",
-                    &serde_json::to_string_pretty(&new_runner)?,
-                );
-                (
-                    new_runner.webhook_endpoint,
-                    self.templates.render(Self::ONLINE_TEMPLATE, &ctx)?,
-                )
-            }
-        };
-        self.send_inbound(cfg, &endpoint, request_body).await
+    async fn deliver_alert(
+        &mut self,
+        cfg: &Config,
+        change: RunnerStateChange,
+        event: &Event,
+    ) -> Result<()> {
+        let request = self.render_event_alert(cfg, &change, event)?;
+        self.send_inbound(cfg, request)
+            .instrument(alert_span(&change))
+            .await
     }
 }
@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::{fs::File, io};
+use tracing_subscriber::{
+    fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter,
+};
+
+use crate::{
+    redact::redact,
+    secrets::resolve_secrets,
+    telemetry::{init_telemetry, OtelConfig, Telemetry},
+};
+
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    // one JSON object per line for log aggregators
+    Json,
+}
+
+// The logging part of the config file.
+// Logging is set up before the config is parsed, so that parsing can be logged as well.
+#[derive(Debug, Deserialize, Default)]
+pub struct LogSettings {
+    pub log_level: Option<String>,
+    pub log_format: Option<LogFormat>,
+    // spans and metrics are exported when this is set
+    pub otel: Option<OtelConfig>,
+}
+
+// Read the log settings from the config file.
+// Problems with the config file are ignored here; they are reported once it is parsed.
+pub fn read_log_settings(cfg_path: &str) -> LogSettings {
+    let mut value: Value = match File::open(cfg_path).map(serde_yaml::from_reader) {
+        Ok(Ok(value)) => value,
+        _ => return LogSettings::default(),
+    };
+    if resolve_secrets(&mut value).is_err() {
+        return LogSettings::default();
+    }
+    serde_yaml::from_value(value).unwrap_or_default()
+}
+
+// Logs are written to stderr so that the output of the subcommands stays parsable.
+// Every line is redacted before it is written.
+struct RedactingWriter;
+
+impl io::Write for RedactingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // the formatter writes every event with a single call
+        io::stderr().write_all(redact(&String::from_utf8_lossy(buf)).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RedactingWriter {
+    type Writer = RedactingWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter
+    }
+}
+
+// The command line takes precedence over the config file.
+// The level is a tracing filter directive, e.g. `debug` or `info,gh_runner_uptime=debug`.
+// Returns the telemetry exporters when they are configured.
+pub fn init_logging(
+    cfg_path: &str,
+    log_level: Option<&str>,
+    log_format: Option<LogFormat>,
+) -> Result<Option<Telemetry>> {
+    let settings = read_log_settings(cfg_path);
+    let log_level = log_level
+        .or(settings.log_level.as_deref())
+        .unwrap_or(DEFAULT_LOG_LEVEL);
+    let filter = EnvFilter::try_new(log_level)
+        .with_context(|| format!("Invalid log level {}", log_level))?;
+    let telemetry = settings
+        .otel
+        .as_ref()
+        .map(init_telemetry)
+        .transpose()
+        .context("Unable to set up the OpenTelemetry export")?;
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(RedactingWriter);
+    match log_format.or(settings.log_format).unwrap_or_default() {
+        LogFormat::Text => builder
+            .finish()
+            .with(telemetry.as_ref().map(Telemetry::layer))
+            .try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .finish()
+            .with(telemetry.as_ref().map(Telemetry::layer))
+            .try_init(),
+    }
+    .map_err(|e| anyhow!(e))?;
+    Ok(telemetry)
+}
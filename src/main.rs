@@ -1,15 +1,50 @@
-use alert::alert_all_changes_and_update_grace_period;
-use anyhow::Result;
-use structs::{Config, RunnerMap};
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::sync::Arc;
+use structs::{MonitorState, SharedState};
 use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::RwLock;
+use tracing::{enabled, error, info, Level};
 
-use crate::{github::get_all_runners, inbound_alert_handler::InboundAlertHandler};
+use crate::{
+    cli::{Cli, Command},
+    event::event_schema,
+    history::{append_history, HistoryEntry},
+    logging::init_logging,
+    redact::redact,
+    report::report_loop,
+    scan::Scanner,
+    status_api::{start_status_api, StatusApiState},
+};
 
 mod alert;
+mod alertmanager;
+mod broker;
+mod check;
+mod cli;
+mod cloudevents;
 mod config;
+mod dashboard;
+mod event;
+mod event_sink;
 mod github;
+mod history;
 mod inbound_alert_handler;
+mod logging;
+mod notifier;
+mod opsgenie;
+mod proxy;
+mod rate_limit;
+mod redact;
+mod report;
+mod scan;
+mod secrets;
+mod signature;
+mod status_api;
 mod structs;
+mod telemetry;
+mod template;
+mod tls;
 
 #[cfg(test)]
 #[path = "./tests/alert_to_inbound_test.rs"]
@@ -18,36 +53,178 @@ mod alert_to_inbound_test;
 #[path = "./tests/alert_unit_test.rs"]
 mod alert_unit_test;
 #[cfg(test)]
+#[path = "./tests/alertmanager_test.rs"]
+mod alertmanager_test;
+#[cfg(test)]
+#[path = "./tests/broker_test.rs"]
+mod broker_test;
+#[cfg(test)]
+#[path = "./tests/check_test.rs"]
+mod check_test;
+#[cfg(test)]
+#[path = "./tests/cli_test.rs"]
+mod cli_test;
+#[cfg(test)]
+#[path = "./tests/cloudevents_test.rs"]
+mod cloudevents_test;
+#[cfg(test)]
+#[path = "./tests/dashboard_test.rs"]
+mod dashboard_test;
+#[cfg(test)]
+#[path = "./tests/event_sink_test.rs"]
+mod event_sink_test;
+#[cfg(test)]
+#[path = "./tests/event_test.rs"]
+mod event_test;
+#[cfg(test)]
+#[path = "./tests/history_test.rs"]
+mod history_test;
+#[cfg(test)]
+#[path = "./tests/logging_test.rs"]
+mod logging_test;
+#[cfg(test)]
+#[path = "./tests/notifier_test.rs"]
+mod notifier_test;
+#[cfg(test)]
+#[path = "./tests/opsgenie_test.rs"]
+mod opsgenie_test;
+#[cfg(test)]
+#[path = "./tests/proxy_test.rs"]
+mod proxy_test;
+#[cfg(test)]
+#[path = "./tests/redact_test.rs"]
+mod redact_test;
+#[cfg(test)]
+#[path = "./tests/reload_test.rs"]
+mod reload_test;
+#[cfg(test)]
+#[path = "./tests/report_test.rs"]
+mod report_test;
+#[cfg(test)]
+#[path = "./tests/scan_test.rs"]
+mod scan_test;
+#[cfg(test)]
+#[path = "./tests/secrets_test.rs"]
+mod secrets_test;
+#[cfg(test)]
+#[path = "./tests/signature_test.rs"]
+mod signature_test;
+#[cfg(test)]
+#[path = "./tests/status_api_test.rs"]
+mod status_api_test;
+#[cfg(test)]
+#[path = "./tests/telemetry_test.rs"]
+mod telemetry_test;
+#[cfg(test)]
+#[path = "./tests/template_test.rs"]
+mod template_test;
+#[cfg(test)]
 #[path = "./tests/test_alert_handler.rs"]
 mod test_alert_handler;
+#[cfg(test)]
+#[path = "./tests/test_fixtures.rs"]
+mod test_fixtures;
+#[cfg(test)]
+#[path = "./tests/tls_test.rs"]
+mod tls_test;
+#[cfg(test)]
+#[path = "./tests/webhook_test.rs"]
+mod webhook_test;
 
-async fn perform_scan(cfg: &Config, runners: &mut RunnerMap) -> Result<()> {
-    println!("Received sighup; starting scan");
-    let mut new_runners = get_all_runners(cfg, false).await?;
-    let mut alert_handler = InboundAlertHandler::new(cfg)?;
-    alert_all_changes_and_update_grace_period(cfg, runners, &mut new_runners, &mut alert_handler)
+// the monitoring daemon, runs forever
+async fn run(cfg_path: &str, dry_run: bool) -> Result<()> {
+    let (mut cfg, runners) = config::load_cfg(cfg_path).await?;
+    if dry_run {
+        info!("Dry run; alerts and reports are printed instead of sent");
+        cfg.dry_run = true;
+    }
+    if let Some(history_path) = &cfg.history_path {
+        let entries: Vec<HistoryEntry> = runners.values().map(HistoryEntry::observed).collect();
+        append_history(history_path, &entries).unwrap_or_else(|e| error!("{:#}", e));
+    }
+    let cfg = Arc::new(cfg);
+    let state: SharedState = Arc::new(RwLock::new(MonitorState::new(&cfg, runners)));
+    let scanner = Arc::new(Scanner::new(cfg.clone(), state)?);
+
+    if let Some(address) = cfg.status_api_address {
+        start_status_api(
+            address,
+            StatusApiState {
+                scanner: scanner.clone(),
+            },
+        )
         .await?;
-    // only update runners when changes got transmitted successfully
-    // -> retry next time when the service remains in the same new state
-    *runners = new_runners;
-    println!("Scan complete; {} runners found", runners.len());
-    Ok(())
-}
+    }
 
-#[tokio::main]
-async fn main() {
-    let (cfg, mut runners) = config::load_cfg("./config.yaml")
-        .await
-        .unwrap_or_else(|e| panic!("Fatal Error: {:#}", e));
+    if let Some(report_interval) = cfg.report_interval {
+        tokio::spawn(report_loop(scanner.clone(), report_interval));
+    }
 
-    println!("Awaiting sighup");
+    info!("Awaiting sighup");
     // wait for sighup from docker_cron container
-    let mut stream = signal(SignalKind::hangup()).unwrap();
+    let mut sighup = signal(SignalKind::hangup()).context("Unable to listen for sighup")?;
+    // sigusr1 reloads the config
+    let mut sigusr1 =
+        signal(SignalKind::user_defined1()).context("Unable to listen for sigusr1")?;
     loop {
         // all errors in this loop only restart the loop, the program doesn't crash any more
-        stream.recv().await;
-        perform_scan(&cfg, &mut runners)
-            .await
-            .unwrap_or_else(|e| eprintln!("Error: {:#}", e));
+        tokio::select! {
+            _ = sighup.recv() => {
+                info!("Received sighup");
+                if let Err(e) = scanner.scan(None).await {
+                    error!("{:#}", e);
+                }
+            }
+            _ = sigusr1.recv() => {
+                info!("Received sigusr1; reloading configuration");
+                if let Err(e) = scanner.reload(cfg_path).await {
+                    error!("Keeping the old configuration: {:#}", e);
+                }
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let telemetry = match init_logging(&cli.config, cli.log_level.as_deref(), cli.log_format) {
+        Ok(telemetry) => telemetry,
+        Err(e) => {
+            eprintln!("Fatal Error: {}", redact(&format!("{:#}", e)));
+            std::process::exit(1);
+        }
+    };
+    let result = match cli.command.unwrap_or(Command::Run { dry_run: false }) {
+        Command::Run { dry_run } => run(&cli.config, dry_run).await,
+        Command::ScanOnce { state } => cli::scan_once(&cli.config, &state).await,
+        Command::CheckConfig { probe_github } => cli::check_config(&cli.config, probe_github).await,
+        Command::ListRunners { format } => cli::list_runners(&cli.config, format).await,
+        Command::UptimeReport { from, to } => {
+            cli::uptime_report(&cli.config, from.as_deref(), to.as_deref())
+        }
+        Command::VerifySignature {
+            secret_file,
+            timestamp,
+            signature,
+            tolerance_seconds,
+        } => cli::verify_signature(&secret_file, timestamp, &signature, tolerance_seconds),
+        Command::EventSchema => {
+            print!("{}", event_schema());
+            Ok(())
+        }
+    };
+    // export everything that is still buffered
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
+    }
+    if let Err(e) = result {
+        // a fatal error must never go unnoticed, not even with a log level of `off`
+        if enabled!(Level::ERROR) {
+            error!("Fatal Error: {:#}", e);
+        } else {
+            eprintln!("Fatal Error: {}", redact(&format!("{:#}", e)));
+        }
+        std::process::exit(1);
     }
 }
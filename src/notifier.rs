@@ -0,0 +1,229 @@
+use anyhow::{bail, Context, Result};
+use reqwest::{header::AUTHORIZATION, RequestBuilder};
+use serde_json::Value;
+use tracing::{debug, error, field::Empty, info_span, Instrument, Span};
+
+use crate::{
+    alert::AlertHandler,
+    alertmanager::AlertmanagerAlertHandler,
+    event::Event,
+    github::runner_key,
+    inbound_alert_handler::InboundAlertHandler,
+    opsgenie::OpsgenieAlertHandler,
+    rate_limit::send_with_retry,
+    redact::redact,
+    structs::{Config, Notifier, Runner, RunnerSetConfig, RunnerStateChange, WebhookAuth},
+    telemetry::trace_context_headers,
+    template::TemplateFormat,
+};
+
+pub const USERNAME: &str = "gh_runner_uptime";
+
+impl Notifier {
+    // the built-in format the messages of the notifier are rendered with, unless format is set
+    pub fn format(&self) -> Option<TemplateFormat> {
+        match self {
+            Notifier::Webhook => None,
+            Notifier::Discord => Some(TemplateFormat::Discord),
+            Notifier::Mattermost => Some(TemplateFormat::Mattermost),
+            Notifier::Alertmanager | Notifier::Opsgenie => None,
+        }
+    }
+
+    // whether the alerts are rendered from the templates
+    pub fn uses_templates(&self) -> bool {
+        matches!(
+            self,
+            Notifier::Webhook | Notifier::Discord | Notifier::Mattermost
+        )
+    }
+
+    // reports are sent to the webhook_endpoint of chat notifiers only
+    pub fn sends_reports(&self) -> bool {
+        matches!(
+            self,
+            Notifier::Webhook | Notifier::Discord | Notifier::Mattermost
+        )
+    }
+}
+
+// routes every alert to the notifier of its runner set
+pub struct NotifierAlertHandler {
+    // the chat notifiers are webhooks with their own built-in format
+    pub webhook: InboundAlertHandler,
+    pub alertmanager: AlertmanagerAlertHandler,
+    pub opsgenie: OpsgenieAlertHandler,
+}
+
+impl NotifierAlertHandler {
+    pub fn new(webhook: InboundAlertHandler) -> Self {
+        NotifierAlertHandler {
+            webhook,
+            alertmanager: AlertmanagerAlertHandler,
+            opsgenie: OpsgenieAlertHandler,
+        }
+    }
+}
+
+impl AlertHandler for NotifierAlertHandler {
+    async fn deliver_alert(
+        &mut self,
+        cfg: &Config,
+        change: RunnerStateChange,
+        event: &Event,
+    ) -> Result<()> {
+        let notifier = cfg
+            .runner_sets
+            .iter()
+            .find(|s| s.name == change.runner().runner_set)
+            .map(|s| s.notifier)
+            // runners of a removed runner set go to their webhook with the defaults
+            .unwrap_or_default();
+        match notifier {
+            Notifier::Webhook | Notifier::Discord | Notifier::Mattermost => {
+                self.webhook.deliver_alert(cfg, change, event).await
+            }
+            Notifier::Alertmanager => self.alertmanager.deliver_alert(cfg, change, event).await,
+            Notifier::Opsgenie => self.opsgenie.deliver_alert(cfg, change, event).await,
+        }
+    }
+}
+
+// The incident tools keep the alerts of offline runners open until they are resolved or closed.
+// Called on reload for the runners of removed runner sets, with the config that still has them.
+// Chat messages and webhooks can't be taken back, so nothing is sent for these notifiers.
+pub async fn close_firing_alerts(cfg: &Config, runners: &[Runner]) -> Result<()> {
+    let mut failed = 0;
+    for runner in runners.iter().filter(|r| r.is_offline()) {
+        let change = RunnerStateChange::Removed(runner.clone());
+        let result = match find_runner_set(cfg, &change)?.notifier {
+            Notifier::Alertmanager => AlertmanagerAlertHandler.send_alert(cfg, change).await,
+            Notifier::Opsgenie => OpsgenieAlertHandler.send_alert(cfg, change).await,
+            Notifier::Webhook | Notifier::Discord | Notifier::Mattermost => continue,
+        };
+        if let Err(e) = result {
+            error!("{:#}", e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "{} firing alerts of removed runner sets could not be closed",
+            failed
+        );
+    }
+    Ok(())
+}
+
+// the first line of an alert, like the title of the built-in formats
+pub fn alert_title(change: &RunnerStateChange) -> String {
+    let state = match change {
+        RunnerStateChange::Created(_) => "was created",
+        RunnerStateChange::Removed(_) => "was removed",
+        RunnerStateChange::Online(_, _) => "is back online",
+        RunnerStateChange::Offline(_, _) => "went offline",
+    };
+    format!("Runner {} {}", change.runner().name, state)
+}
+
+// at most max characters, ending in … when cut
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return String::from(text);
+    }
+    let cut: String = text.chars().take(max.saturating_sub(1)).collect();
+    format!("{}…", cut)
+}
+
+// the runner set of an alert sent by a dedicated notifier
+pub fn find_runner_set<'a>(
+    cfg: &'a Config,
+    change: &RunnerStateChange,
+) -> Result<&'a RunnerSetConfig> {
+    let name = &change.runner().runner_set;
+    cfg.runner_sets
+        .iter()
+        .find(|s| &s.name == name)
+        .with_context(|| format!("unknown runner set {}", name))
+}
+
+pub fn alert_span(change: &RunnerStateChange) -> Span {
+    let runner = change.runner();
+    info_span!(
+        "alert",
+        kind = change.kind(),
+        runner_key = runner_key(&runner.runner_set, runner.id)
+    )
+}
+
+pub fn apply_auth(builder: RequestBuilder, auth: Option<&WebhookAuth>) -> RequestBuilder {
+    match auth {
+        Some(WebhookAuth::Bearer(token)) => builder.bearer_auth(token),
+        Some(WebhookAuth::Basic { username, password }) => {
+            builder.basic_auth(username, password.as_ref())
+        }
+        Some(WebhookAuth::GenieKey(key)) => {
+            builder.header(AUTHORIZATION, format!("GenieKey {}", key))
+        }
+        None => builder,
+    }
+}
+
+// posts a message to the webhook_endpoint of the runner set, retrying when rate limited
+pub async fn post_json(
+    cfg: &Config,
+    runner_set: &RunnerSetConfig,
+    receiver: &str,
+    message: &Value,
+) -> Result<()> {
+    post_json_to(
+        cfg,
+        runner_set,
+        &runner_set.webhook_endpoint,
+        receiver,
+        message,
+    )
+    .await
+}
+
+// posts a message with the client and the auth of the runner set
+pub async fn post_json_to(
+    cfg: &Config,
+    runner_set: &RunnerSetConfig,
+    url: &str,
+    receiver: &str,
+    message: &Value,
+) -> Result<()> {
+    if cfg.dry_run {
+        println!("{}", redact(&serde_json::to_string_pretty(message)?));
+        return Ok(());
+    }
+    debug!("Sending to {}:\n{}", receiver, message);
+
+    let span = info_span!("webhook_delivery", http.status_code = Empty);
+    let builder = runner_set
+        .webhook_client
+        .post(url)
+        // lets the receiver continue the trace
+        .headers(span.in_scope(trace_context_headers))
+        .json(message);
+    let resp = send_with_retry(
+        apply_auth(builder, runner_set.webhook.auth.as_ref()),
+        cfg.inbound_timeout,
+    )
+    .instrument(span.clone())
+    .await
+    // the url contains the webhook's secret
+    .map_err(|e| e.without_url())
+    .with_context(|| format!("{} request failed", receiver))?;
+    span.record("http.status_code", resp.status().as_u16());
+    if !resp.status().is_success() {
+        bail!(
+            "{} request returned status {}; return body: {}",
+            receiver,
+            resp.status(),
+            resp.text().await?
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::Instrument;
+
+use crate::{
+    alert::AlertHandler,
+    event::Event,
+    github::{get_github_settings_url, runner_key},
+    notifier::{alert_span, alert_title, find_runner_set, post_json_to, truncate, USERNAME},
+    structs::{Config, Runner, RunnerSetConfig, RunnerStateChange},
+};
+
+// https://docs.opsgenie.com/docs/alert-api#create-alert
+const MESSAGE_LIMIT: usize = 130;
+
+// Creates an alert for every offline runner with the Opsgenie alert API and closes it by its
+// alias once the runner is back online or removed.
+// The webhook_endpoint is the API, e.g. https://api.opsgenie.com or https://api.eu.opsgenie.com
+pub struct OpsgenieAlertHandler;
+
+// Opsgenie deduplicates open alerts with the same alias.
+// The runner key contains slashes and may be too long, so it is hashed.
+pub fn opsgenie_alias(runner: &Runner) -> String {
+    let key = runner_key(&runner.runner_set, runner.id);
+    format!("gh_runner_uptime-{}", hex::encode(Sha256::digest(key)))
+}
+
+pub fn create_request(runner_set: &RunnerSetConfig, change: &RunnerStateChange) -> Value {
+    let runner = change.runner();
+    json!({
+        "message": truncate(&alert_title(change), MESSAGE_LIMIT),
+        "alias": opsgenie_alias(runner),
+        "description": format!(
+            "Runner {} of {} is offline.\n{}",
+            runner.name,
+            runner_set.slug,
+            get_github_settings_url(&runner_set.github_base_uri, runner_set.kind, &runner_set.slug)
+        ),
+        "entity": runner_set.slug,
+        "source": USERNAME,
+        "tags": [USERNAME, runner_set.kind.as_str()],
+        "details": {
+            "runner_key": runner_key(&runner.runner_set, runner.id),
+            "runner_set": runner_set.slug,
+            "runner": runner.name,
+            "runner_id": runner.id.to_string(),
+            "os": runner.os,
+            "runner_labels": runner.labels.join(", "),
+        },
+    })
+}
+
+pub fn close_request(change: &RunnerStateChange) -> Value {
+    json!({
+        "source": USERNAME,
+        "note": alert_title(change),
+    })
+}
+
+impl AlertHandler for OpsgenieAlertHandler {
+    async fn deliver_alert(
+        &mut self,
+        cfg: &Config,
+        change: RunnerStateChange,
+        _event: &Event,
+    ) -> Result<()> {
+        let create = match &change {
+            RunnerStateChange::Offline(_, _) => true,
+            RunnerStateChange::Created(runner) if runner.is_offline() => true,
+            RunnerStateChange::Online(_, _) | RunnerStateChange::Removed(_) => false,
+            // nothing to create or close
+            RunnerStateChange::Created(_) => return Ok(()),
+        };
+        let runner_set = find_runner_set(cfg, &change)?;
+        let api = runner_set.webhook_endpoint.trim_end_matches('/');
+        let (url, request) = if create {
+            (
+                format!("{}/v2/alerts", api),
+                create_request(runner_set, &change),
+            )
+        } else {
+            // the alias is hex, so it can be used in the path as it is
+            (
+                format!(
+                    "{}/v2/alerts/{}/close?identifierType=alias",
+                    api,
+                    opsgenie_alias(change.runner())
+                ),
+                close_request(&change),
+            )
+        };
+        post_json_to(cfg, runner_set, &url, "Opsgenie", &request)
+            .instrument(alert_span(&change))
+            .await
+    }
+}
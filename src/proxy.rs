@@ -0,0 +1,52 @@
+use anyhow::{bail, Context, Result};
+use reqwest::{ClientBuilder, NoProxy, Proxy};
+use serde::Deserialize;
+
+const DIRECT: &str = "direct";
+// reqwest panics when credentials are set for the socks4 schemes it supports as well
+const SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+// the proxy of a GitHub or webhook endpoint
+// without one the HTTP_PROXY, HTTPS_PROXY and NO_PROXY environment variables are used
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ProxyConfig {
+    // http://, https://, socks5:// or socks5h:// (resolves the host on the proxy)
+    // `direct` never uses a proxy, not even one from the environment
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // hosts, domains (e.g. `.example.com`) and IP ranges that are connected to directly
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+pub fn apply_proxy(builder: ClientBuilder, proxy: Option<&ProxyConfig>) -> Result<ClientBuilder> {
+    let Some(proxy) = proxy else {
+        return Ok(builder);
+    };
+    if proxy.url == DIRECT {
+        return Ok(builder.no_proxy());
+    }
+    // a url without a scheme is an http proxy
+    if let Some((scheme, _)) = proxy.url.split_once("://") {
+        if !SCHEMES.contains(&scheme.to_lowercase().as_str()) {
+            bail!(
+                "Unsupported proxy scheme {}, use one of {}",
+                scheme,
+                SCHEMES.join(", ")
+            );
+        }
+    }
+    // the url itself isn't part of the error, it may contain a password
+    let mut reqwest_proxy = Proxy::all(&proxy.url).context("Invalid proxy url")?;
+    if let Some(username) = &proxy.username {
+        reqwest_proxy =
+            reqwest_proxy.basic_auth(username, proxy.password.as_deref().unwrap_or_default());
+    }
+    if !proxy.no_proxy.is_empty() {
+        reqwest_proxy = reqwest_proxy.no_proxy(NoProxy::from_string(&proxy.no_proxy.join(",")));
+    }
+    // the configured proxy replaces the ones from the environment
+    Ok(builder.no_proxy().proxy(reqwest_proxy))
+}
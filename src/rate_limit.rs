@@ -0,0 +1,66 @@
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+use tracing::warn;
+
+// how often a rate limited request is retried before giving up
+const MAX_RETRIES: u32 = 3;
+const DEFAULT_WAIT: Duration = Duration::from_secs(1);
+
+// Sends the request and retries it when the receiver answers 429 Too Many Requests.
+// The wait is taken from the Retry-After header, or the retry_after field Discord puts in the body.
+// All waits together take at most max_wait, so a scan isn't held up by a single receiver.
+// The last 429 response is returned when the receiver keeps rate limiting; the next scan retries.
+pub async fn send_with_retry(
+    builder: RequestBuilder,
+    max_wait: Duration,
+) -> reqwest::Result<Response> {
+    let mut retries = 0;
+    let mut remaining = max_wait;
+    loop {
+        // only streaming bodies can't be cloned
+        let Some(retry) = builder.try_clone() else {
+            return builder.send().await;
+        };
+        let resp = retry.send().await?;
+        if resp.status() != StatusCode::TOO_MANY_REQUESTS
+            || retries == MAX_RETRIES
+            || remaining.is_zero()
+        {
+            return Ok(resp);
+        }
+        retries += 1;
+        let wait = retry_after(resp).await.min(remaining);
+        remaining -= wait;
+        warn!(
+            "Rate limited by the webhook; retrying in {:.1}s ({}/{})",
+            wait.as_secs_f64(),
+            retries,
+            MAX_RETRIES
+        );
+        tokio::time::sleep(wait).await;
+    }
+}
+
+async fn retry_after(resp: Response) -> Duration {
+    // only the number of seconds is supported, not the HTTP date
+    let header = resp
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_seconds);
+    if let Some(wait) = header {
+        return wait;
+    }
+    // e.g. {"message": "You are being rate limited.", "retry_after": 0.5, "global": false}
+    resp.json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|body| body.get("retry_after")?.as_f64())
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .unwrap_or(DEFAULT_WAIT)
+}
+
+// Retry-After is an integer, but some receivers send fractions
+fn parse_seconds(value: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(value.trim().parse().ok()?).ok()
+}
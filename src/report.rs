@@ -0,0 +1,276 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use serde::Serialize;
+use std::{cmp::Reverse, collections::BTreeMap, sync::Arc};
+use tracing::{error, info};
+
+use crate::{
+    dashboard::format_duration,
+    history::{load_history, HistoryEntry},
+    inbound_alert_handler::InboundAlertHandler,
+    scan::Scanner,
+    structs::{Config, MonitorState, ReportInterval, RunnerSetConfig, SharedState},
+    template::Templates,
+};
+
+// how many runners are listed in the longest_outages and flappiest_runners rankings
+const RANKING_LEN: usize = 5;
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct ReportRunner {
+    pub name: String,
+    pub id: i64,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct OfflineRunner {
+    pub runner: ReportRunner,
+    pub offline_since: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct Outage {
+    pub runner: ReportRunner,
+    pub start: String,
+    // None when the runner is still offline
+    pub end: Option<String>,
+    // only the part of the outage within the report period counts
+    pub duration_seconds: i64,
+    pub duration: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct FlappingRunner {
+    pub runner: ReportRunner,
+    // number of online and offline alerts
+    pub state_changes: usize,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct LifecycleEvent {
+    pub runner: ReportRunner,
+    pub utc_time: String,
+}
+
+// the statistics available to the report template as `report`
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct RunnerSetReport {
+    pub runner_set: String,
+    pub from: String,
+    pub to: String,
+    pub runner_count: usize,
+    pub offline_runners: Vec<OfflineRunner>,
+    pub longest_outages: Vec<Outage>,
+    pub flappiest_runners: Vec<FlappingRunner>,
+    pub created_runners: Vec<LifecycleEvent>,
+    pub removed_runners: Vec<LifecycleEvent>,
+}
+
+// reports are sent at midnight UTC, weekly reports on Mondays
+pub fn next_report_time(interval: ReportInterval, now: DateTime<Utc>) -> DateTime<Utc> {
+    let today = Utc
+        .with_ymd_and_hms(now.year(), now.month(), now.day(), 0, 0, 0)
+        .single()
+        .unwrap_or(now);
+    match interval {
+        ReportInterval::Daily => today + Duration::days(1),
+        ReportInterval::Weekly => {
+            today + Duration::days(7 - now.weekday().num_days_from_monday() as i64)
+        }
+    }
+}
+
+pub fn report_period(interval: ReportInterval) -> Duration {
+    match interval {
+        ReportInterval::Daily => Duration::days(1),
+        ReportInterval::Weekly => Duration::weeks(1),
+    }
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+pub fn compute_runner_set_report(
+    runner_set: &str,
+    state: &MonitorState,
+    history: &[HistoryEntry],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> RunnerSetReport {
+    let report_runner = |e: &HistoryEntry| ReportRunner {
+        name: e.runner_name.clone(),
+        id: e.runner_id,
+    };
+    let in_period = |time: &DateTime<Utc>| from <= *time && *time < to;
+
+    // runner id -> entries sorted by time
+    let mut timelines: BTreeMap<i64, Vec<(DateTime<Utc>, &HistoryEntry)>> = BTreeMap::new();
+    for entry in history.iter().filter(|e| e.runner_set == runner_set) {
+        if let Some(time) = parse_time(&entry.utc_time) {
+            timelines
+                .entry(entry.runner_id)
+                .or_default()
+                .push((time, entry));
+        }
+    }
+
+    let mut outages = vec![];
+    let mut flappiest_runners = vec![];
+    let mut created_runners = vec![];
+    let mut removed_runners = vec![];
+    for timeline in timelines.values_mut() {
+        timeline.sort_by_key(|(time, _)| *time);
+        let mut offline_since: Option<(DateTime<Utc>, &HistoryEntry)> = None;
+        let mut state_changes = 0;
+        for (time, entry) in timeline.iter() {
+            if in_period(time) {
+                match entry.kind.as_str() {
+                    "online" | "offline" => state_changes += 1,
+                    "created" => created_runners.push(LifecycleEvent {
+                        runner: report_runner(entry),
+                        utc_time: entry.utc_time.clone(),
+                    }),
+                    "removed" => removed_runners.push(LifecycleEvent {
+                        runner: report_runner(entry),
+                        utc_time: entry.utc_time.clone(),
+                    }),
+                    _ => (),
+                }
+            }
+            // repeated offline entries (e.g. from retried alerts) belong to the same outage
+            match (entry.online, offline_since) {
+                (Some(false), None) => offline_since = Some((*time, entry)),
+                (Some(false), Some(_)) => (),
+                (_, Some((start, start_entry))) => {
+                    outages.push((start, Some(*time), start_entry));
+                    offline_since = None;
+                }
+                (_, None) => (),
+            }
+        }
+        if let Some((start, start_entry)) = offline_since {
+            outages.push((start, None, start_entry));
+        }
+        if let (true, Some((_, last))) = (state_changes > 0, timeline.last()) {
+            flappiest_runners.push(FlappingRunner {
+                runner: report_runner(last),
+                state_changes,
+            });
+        }
+    }
+
+    let mut longest_outages: Vec<Outage> = outages
+        .into_iter()
+        .filter_map(|(start, end, entry)| {
+            let seconds = (end.unwrap_or(to).min(to) - start.max(from)).num_seconds();
+            (seconds > 0).then(|| Outage {
+                runner: report_runner(entry),
+                start: start.to_rfc3339(),
+                end: end.map(|e| e.to_rfc3339()),
+                duration_seconds: seconds,
+                duration: format_duration(Duration::seconds(seconds)),
+            })
+        })
+        .collect();
+    longest_outages.sort_by_key(|o| Reverse(o.duration_seconds));
+    longest_outages.truncate(RANKING_LEN);
+    flappiest_runners.sort_by_key(|f| Reverse(f.state_changes));
+    flappiest_runners.truncate(RANKING_LEN);
+
+    let mut offline_runners: Vec<OfflineRunner> = state
+        .runners
+        .iter()
+        .filter(|(_, r)| r.runner_set == runner_set && r.is_offline())
+        .map(|(key, r)| OfflineRunner {
+            runner: ReportRunner {
+                name: r.name.clone(),
+                id: r.id,
+            },
+            offline_since: state.state_since.get(key).map(|t| t.to_rfc3339()),
+        })
+        .collect();
+    offline_runners.sort_by(|a, b| a.runner.name.cmp(&b.runner.name));
+
+    RunnerSetReport {
+        runner_set: runner_set.to_string(),
+        from: from.to_rfc3339(),
+        to: to.to_rfc3339(),
+        runner_count: state
+            .runners
+            .values()
+            .filter(|r| r.runner_set == runner_set)
+            .count(),
+        offline_runners,
+        longest_outages,
+        flappiest_runners,
+        created_runners,
+        removed_runners,
+    }
+}
+
+// send one report per runner set to its webhook
+pub async fn send_reports(
+    cfg: &Config,
+    templates: Arc<Templates>,
+    state: &SharedState,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<()> {
+    let history_path = cfg
+        .history_path
+        .as_ref()
+        .context("reports need history_path to be configured")?;
+    let history = load_history(history_path)?;
+    let alert_handler = InboundAlertHandler::with_templates(templates);
+    let reports: Vec<(&RunnerSetConfig, RunnerSetReport)> = {
+        let state = state.read().await;
+        cfg.runner_sets
+            .iter()
+            .map(|s| {
+                (
+                    s,
+                    compute_runner_set_report(&s.name, &state, &history, from, to),
+                )
+            })
+            .collect()
+    };
+    // one unreachable webhook must not keep the other runner sets from their reports
+    let mut failed = vec![];
+    for (runner_set, report) in reports {
+        if !runner_set.notifier.sends_reports() {
+            continue;
+        }
+        if let Err(e) = alert_handler.send_report(cfg, runner_set, &report).await {
+            error!("Failed to send the report of {}: {:#}", runner_set.name, e);
+            failed.push(runner_set.name.as_str());
+        }
+    }
+    if !failed.is_empty() {
+        bail!("Failed to send the reports of {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+// runs forever
+// the config is read again for every report to pick up reloads
+pub async fn report_loop(scanner: Arc<Scanner>, interval: ReportInterval) {
+    loop {
+        let next = next_report_time(interval, Utc::now());
+        info!("Next report scheduled for {}", next.to_rfc3339());
+        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+        info!("Sending reports");
+        send_reports(
+            &scanner.cfg(),
+            scanner.templates(),
+            &scanner.state,
+            next - report_period(interval),
+            next,
+        )
+        .await
+        .unwrap_or_else(|e| error!("{:#}", e));
+    }
+}
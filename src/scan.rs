@@ -0,0 +1,289 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, RwLock,
+};
+use tokio::sync::Mutex;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::{
+    alert::{alert_all_changes_and_update_grace_period, RecordingAlertHandler},
+    alertmanager::refresh_firing_alerts,
+    broker::close_unused,
+    config::parse_cfg,
+    event_sink::EventSinkAlertHandler,
+    github::get_runners_for_sets,
+    history::{write_history, HistoryEntry},
+    inbound_alert_handler::InboundAlertHandler,
+    notifier::{close_firing_alerts, NotifierAlertHandler},
+    structs::{Config, DeliveredAlert, Runner, RunnerSetConfig, SharedState},
+    telemetry::record_runner_counts,
+    template::Templates,
+};
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct ScanReport {
+    pub scanned_runner_sets: Vec<String>,
+    pub runner_count: usize,
+    // the state changes that got alerted during this scan
+    pub alerts: Vec<DeliveredAlert>,
+}
+
+struct CompletedScan {
+    generation: u64,
+    // None means all runner sets were scanned
+    only_runner_set: Option<String>,
+    // anyhow errors can't be cloned
+    result: Result<ScanReport, String>,
+}
+
+// Scans can be triggered from different places (sighup, the http api).
+// Only one scan runs at a time. All triggers that arrive while a scan is running are coalesced
+// into a single follow-up scan and share its result.
+// The config can be reloaded while the scanner is running; reloads are mutually exclusive with
+// scans as well.
+pub struct Scanner {
+    cfg: RwLock<Arc<Config>>,
+    // compiled once per config load
+    templates: RwLock<Arc<Templates>>,
+    pub state: SharedState,
+    started_generation: AtomicU64,
+    last_scan: Mutex<CompletedScan>,
+}
+
+impl CompletedScan {
+    fn none() -> Self {
+        CompletedScan {
+            generation: 0,
+            only_runner_set: None,
+            result: Err(String::from("no scan performed yet")),
+        }
+    }
+}
+
+impl Scanner {
+    pub fn new(cfg: Arc<Config>, state: SharedState) -> Result<Self> {
+        let templates = Templates::new(&cfg)?;
+        Ok(Scanner {
+            cfg: RwLock::new(cfg),
+            templates: RwLock::new(Arc::new(templates)),
+            state,
+            started_generation: AtomicU64::new(0),
+            last_scan: Mutex::new(CompletedScan::none()),
+        })
+    }
+
+    // the currently active config
+    pub fn cfg(&self) -> Arc<Config> {
+        self.cfg.read().expect("config lock poisoned").clone()
+    }
+
+    // Parse and validate the config at cfg_path and apply it.
+    // The old config remains active when anything goes wrong.
+    pub async fn reload(&self, cfg_path: &str) -> Result<()> {
+        // no scan may run while the runner sets change
+        let mut last_scan = self.last_scan.lock().await;
+        let old_cfg = self.cfg();
+        let new_cfg = Arc::new(Config {
+            dry_run: old_cfg.dry_run,
+            ..parse_cfg(cfg_path, old_cfg.allow_http)?
+        });
+        // make sure alerts can be rendered before applying anything
+        let new_templates = Arc::new(Templates::new(&new_cfg)?);
+
+        let is_in = |cfg: &Config, name: &str| cfg.runner_sets.iter().any(|s| s.name == name);
+        let added_sets: Vec<&RunnerSetConfig> = new_cfg
+            .runner_sets
+            .iter()
+            .filter(|s| !is_in(&old_cfg, &s.name))
+            .collect();
+        let removed_sets: Vec<String> = old_cfg
+            .runner_sets
+            .iter()
+            .filter(|s| !is_in(&new_cfg, &s.name))
+            .map(|s| s.name.clone())
+            .collect();
+        // new runner sets are initialized like on startup, without sending alerts
+        let added_runners = get_runners_for_sets(&added_sets, true).await?;
+
+        if old_cfg.status_api_address != new_cfg.status_api_address {
+            warn!("Changing status_api_address requires a restart");
+        }
+        if old_cfg.report_interval != new_cfg.report_interval {
+            warn!("Changing report_interval requires a restart");
+        }
+
+        let mut history = vec![];
+        let mut removed_runners = vec![];
+        {
+            let mut state = self.state.write().await;
+            for runner in state.runners.values() {
+                if removed_sets.contains(&runner.runner_set) {
+                    history.push(HistoryEntry::unmonitored(runner));
+                    removed_runners.push(runner.clone());
+                }
+            }
+            state.remove_runner_sets(&removed_sets);
+            // the webhook of unchanged runner sets might have changed
+            for runner in state.runners.values_mut() {
+                if let Some(s) = new_cfg
+                    .runner_sets
+                    .iter()
+                    .find(|s| s.name == runner.runner_set)
+                {
+                    runner.webhook_endpoint = s.webhook_endpoint.clone();
+                    runner.webhook_cloudevents = s.webhook.cloudevents;
+                }
+            }
+            history.extend(added_runners.values().map(HistoryEntry::observed));
+            state.record_scan(&added_sets);
+            state.update_runners(&added_sets, added_runners);
+        }
+        if let Some(history_path) = &new_cfg.history_path {
+            write_history(history_path.clone(), history)
+                .await
+                .unwrap_or_else(|e| error!("{:#}", e));
+        }
+        // the removed runner sets won't be scanned again to resolve them
+        close_firing_alerts(&old_cfg, &removed_runners)
+            .await
+            .unwrap_or_else(|e| error!("{:#}", e));
+        info!(
+            "Configuration reloaded; {} runner sets added, {} removed",
+            added_sets.len(),
+            removed_sets.len()
+        );
+        *self.cfg.write().expect("config lock poisoned") = new_cfg.clone();
+        *self.templates.write().expect("templates lock poisoned") = new_templates;
+        close_unused(&new_cfg.event_sinks).await;
+        // results from before the reload must not be reused
+        *last_scan = CompletedScan::none();
+        Ok(())
+    }
+
+    // compiled from the currently active config
+    pub fn templates(&self) -> Arc<Templates> {
+        self.templates
+            .read()
+            .expect("templates lock poisoned")
+            .clone()
+    }
+
+    // scan all runner sets if only_runner_set is None
+    pub async fn scan(&self, only_runner_set: Option<&str>) -> Result<ScanReport> {
+        // every scan started after this point has seen the world after the trigger
+        let triggered_after = self.started_generation.load(Ordering::SeqCst);
+        let mut last_scan = self.last_scan.lock().await;
+        let covered = match (&last_scan.only_runner_set, only_runner_set) {
+            (None, _) => true,
+            (Some(last), Some(requested)) => last == requested,
+            (Some(_), None) => false,
+        };
+        if last_scan.generation > triggered_after && covered {
+            info!("Scan already performed while waiting; reusing its result");
+            return last_scan.result.clone().map_err(|e| anyhow!(e));
+        }
+
+        let generation = self.started_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let span = info_span!(
+            "scan",
+            scan_id = generation,
+            runner_sets = only_runner_set.unwrap_or("all")
+        );
+        let result = perform_scan(&self.cfg(), self.templates(), &self.state, only_runner_set)
+            .instrument(span)
+            .await;
+        *last_scan = CompletedScan {
+            generation,
+            only_runner_set: only_runner_set.map(String::from),
+            result: result
+                .as_ref()
+                .map(Clone::clone)
+                .map_err(|e| format!("{:#}", e)),
+        };
+        result
+    }
+}
+
+async fn perform_scan(
+    cfg: &Config,
+    templates: Arc<Templates>,
+    state: &SharedState,
+    only_runner_set: Option<&str>,
+) -> Result<ScanReport> {
+    let runner_sets: Vec<&RunnerSetConfig> = match only_runner_set {
+        None => cfg.runner_sets.iter().collect(),
+        Some(name) => vec![cfg
+            .runner_sets
+            .iter()
+            .find(|s| s.name == name)
+            .with_context(|| format!("unknown runner set {}", name))?],
+    };
+    let is_scanned = |runner_set: &str| runner_sets.iter().any(|s| s.name == runner_set);
+    info!("Starting scan of {} runner sets", runner_sets.len());
+
+    let mut new_runners = get_runners_for_sets(&runner_sets, false).await?;
+    let mut inbound_alert_handler = InboundAlertHandler::with_templates(templates);
+    // don't block the status api while the alerts are being sent
+    let old_runners = {
+        let state = state.read().await;
+        inbound_alert_handler.state_since = state.state_since.clone();
+        state
+            .runners
+            .iter()
+            .filter(|(_, r)| is_scanned(&r.runner_set))
+            .map(|(k, r)| (k.clone(), r.clone()))
+            .collect()
+    };
+    let mut alert_handler = RecordingAlertHandler::new(EventSinkAlertHandler::new(
+        NotifierAlertHandler::new(inbound_alert_handler),
+    ));
+    let alert_result = alert_all_changes_and_update_grace_period(
+        cfg,
+        &old_runners,
+        &mut new_runners,
+        &mut alert_handler,
+    )
+    .await;
+
+    let mut state = state.write().await;
+    state.record_alerts(&alert_handler.delivered);
+    // only update runners when changes got transmitted successfully
+    // -> retry next time when the service remains in the same new state
+    let runner_count = new_runners.len();
+    let offline_runners: Vec<Runner> = new_runners
+        .values()
+        .filter(|r| r.is_offline())
+        .cloned()
+        .collect();
+    if alert_result.is_ok() {
+        // the scan only counts as successful once its alerts are delivered
+        state.record_scan(&runner_sets);
+        state.update_runners(&runner_sets, new_runners);
+        record_runner_counts(&state, &runner_sets);
+    }
+    drop(state);
+    if let Some(history_path) = &cfg.history_path {
+        let entries: Vec<HistoryEntry> = alert_handler
+            .delivered
+            .iter()
+            .map(HistoryEntry::from_alert)
+            .collect();
+        // a broken history must not stop the alerting
+        write_history(history_path.clone(), entries)
+            .await
+            .unwrap_or_else(|e| error!("{:#}", e));
+    }
+    alert_result?;
+    // a failed refresh is fixed by the next scan, as long as it's within the resolve_timeout
+    refresh_firing_alerts(cfg, &offline_runners)
+        .await
+        .unwrap_or_else(|e| error!("{:#}", e));
+    info!("Scan complete; {} runners found", runner_count);
+    Ok(ScanReport {
+        scanned_runner_sets: runner_sets.iter().map(|s| s.name.clone()).collect(),
+        runner_count,
+        alerts: alert_handler.delivered,
+    })
+}
@@ -0,0 +1,99 @@
+use anyhow::{bail, ensure, Context, Result};
+use serde_yaml::{Mapping, Value};
+
+// printed instead of secrets
+pub const REDACTED: &str = "<redacted>";
+
+// Keys that may be given as `<key>_file` instead, anywhere in the config.
+// The file contains the value; a trailing newline is removed.
+const FILE_KEYS: [&str; 6] = [
+    "github_pat",
+    "webhook_endpoint",
+    "webhook_bearer_token",
+    "webhook_signing_secret",
+    "opsgenie_api_key",
+    "scan_api_token",
+];
+
+// the settings whose password may be given as `password_file`
+const PASSWORD_KEYS: [&str; 3] = ["webhook_basic_auth", "github_proxy", "webhook_proxy"];
+
+// Replace `${NAME}` with the value of the environment variable NAME.
+// `$$` is a literal `$`.
+pub fn interpolate_env(value: &str) -> Result<String> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("$$") {
+            out.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let Some(end) = after.find('}') else {
+                bail!("unterminated ${{ in config value");
+            };
+            let name = &after[..end];
+            let env_value = std::env::var(name)
+                .with_context(|| format!("environment variable {} is not set", name))?;
+            out.push_str(&env_value);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+// interpolate environment variables in all strings and read all `<key>_file` variants
+pub fn resolve_secrets(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(s) => *s = interpolate_env(s)?,
+        Value::Sequence(values) => {
+            for value in values {
+                resolve_secrets(value)?;
+            }
+        }
+        Value::Mapping(map) => {
+            for (key, value) in map.iter_mut() {
+                resolve_secrets(value)?;
+                if let (Some(key), Value::Mapping(settings)) = (key.as_str(), value) {
+                    if PASSWORD_KEYS.contains(&key) {
+                        read_file_keys(settings, &["password"])?;
+                    }
+                }
+            }
+            read_file_keys(map, &FILE_KEYS)?;
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+// replaces every `<key>_file` with the content of the file
+fn read_file_keys(map: &mut Mapping, keys: &[&str]) -> Result<()> {
+    for key in keys {
+        let file_key = format!("{}_file", key);
+        let Some(path) = map.remove(file_key.as_str()) else {
+            continue;
+        };
+        ensure!(
+            !map.contains_key(key),
+            "only one of {} and {} may be set",
+            key,
+            file_key
+        );
+        let path = path
+            .as_str()
+            .with_context(|| format!("{} needs to be a path", file_key))?;
+        let secret = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read {} {}", file_key, path))?;
+        map.insert(
+            Value::from(*key),
+            Value::from(secret.trim_end_matches(['\r', '\n'])),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use anyhow::{Context, Result};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_LENGTH},
+        HeaderMap, StatusCode,
+    },
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{net::SocketAddr, sync::Arc};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use crate::{
+    dashboard::get_dashboard,
+    github::runner_key,
+    history::{compute_uptime, load_history, parse_report_window, UptimeReport},
+    redact::redact,
+    scan::{ScanReport, Scanner},
+    structs::{Runner, RunnerMap, RunnerSetStatus},
+};
+
+// everything but triggering a scan is read-only
+#[derive(Clone)]
+pub struct StatusApiState {
+    pub scanner: Arc<Scanner>,
+}
+
+#[derive(Debug, Serialize)]
+struct RunnerSetResponse {
+    name: String,
+    github_endpoint: String,
+    runner_count: usize,
+    #[serde(flatten)]
+    status: RunnerSetStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct UptimeQuery {
+    // rfc3339 timestamps; the last 30 days by default
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScanQuery {
+    // only scan this runner set
+    runner_set: Option<String>,
+}
+
+pub fn status_api_router(api_state: StatusApiState) -> Router {
+    Router::new()
+        .route("/", get(get_dashboard))
+        .route("/healthz", get(get_healthz))
+        .route("/sets", get(get_sets))
+        .route("/runners", get(get_runners))
+        .route("/runners/:set/:id", get(get_runner))
+        .route("/scan", post(post_scan))
+        .route("/uptime", get(get_uptime))
+        .layer(middleware::map_response(redact_response))
+        .with_state(api_state)
+}
+
+// bind the listener before returning so that a bad address is noticed at startup
+pub async fn start_status_api(address: SocketAddr, api_state: StatusApiState) -> Result<()> {
+    let listener = TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Unable to bind status api to {}", address))?;
+    info!("Status api listening on {}", address);
+    tokio::spawn(async move {
+        axum::serve(listener, status_api_router(api_state))
+            .await
+            .unwrap_or_else(|e| error!("Status api error: {:#}", e));
+    });
+    Ok(())
+}
+
+// no response may contain a secret, e.g. in the message of a failed scan
+async fn redact_response(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            String::from("unable to read the response"),
+        );
+    };
+    let body = match std::str::from_utf8(&bytes) {
+        Ok(text) => Body::from(redact(text)),
+        Err(_) => Body::from(bytes),
+    };
+    // the length changes with the redaction
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, body)
+}
+
+fn error_response(status: StatusCode, msg: String) -> Response {
+    (status, Json(json!({ "error": msg }))).into_response()
+}
+
+// compare in constant time to not leak the token through response timings
+fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+async fn get_healthz() -> Json<serde_json::Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+async fn get_sets(State(api_state): State<StatusApiState>) -> Json<Vec<RunnerSetResponse>> {
+    let cfg = api_state.scanner.cfg();
+    let state = api_state.scanner.state.read().await;
+    Json(
+        cfg.runner_sets
+            .iter()
+            .map(|runner_set| RunnerSetResponse {
+                name: runner_set.name.clone(),
+                github_endpoint: runner_set.github_endpoint.clone(),
+                runner_count: state
+                    .runners
+                    .values()
+                    .filter(|r| r.runner_set == runner_set.name)
+                    .count(),
+                status: state
+                    .runner_sets
+                    .get(&runner_set.name)
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+            .collect(),
+    )
+}
+
+async fn get_runners(State(api_state): State<StatusApiState>) -> Json<RunnerMap> {
+    Json(api_state.scanner.state.read().await.runners.clone())
+}
+
+// the set is the full runner set name, percent-encoded
+async fn get_runner(
+    State(api_state): State<StatusApiState>,
+    Path((set, id)): Path<(String, i64)>,
+) -> Result<Json<Runner>, Response> {
+    let state = api_state.scanner.state.read().await;
+    match state.runners.get(&runner_key(&set, id)) {
+        Some(runner) => Ok(Json(runner.clone())),
+        None => Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("runner {} not found in {}", id, set),
+        )),
+    }
+}
+
+// requires the scan_api_token as bearer token
+async fn post_scan(
+    State(api_state): State<StatusApiState>,
+    headers: HeaderMap,
+    Query(query): Query<ScanQuery>,
+) -> Result<Json<ScanReport>, Response> {
+    let cfg = api_state.scanner.cfg();
+    let token = match &cfg.scan_api_token {
+        Some(token) => token,
+        None => {
+            return Err(error_response(
+                StatusCode::FORBIDDEN,
+                String::from("scanning via the api is disabled; set scan_api_token"),
+            ))
+        }
+    };
+    let authorized = headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .is_some_and(|given| token_matches(token, given));
+    if !authorized {
+        return Err(error_response(
+            StatusCode::UNAUTHORIZED,
+            String::from("missing or wrong bearer token"),
+        ));
+    }
+
+    if let Some(runner_set) = &query.runner_set {
+        if !cfg.runner_sets.iter().any(|s| &s.name == runner_set) {
+            return Err(error_response(
+                StatusCode::NOT_FOUND,
+                format!("unknown runner set {}", runner_set),
+            ));
+        }
+    }
+
+    info!("Received scan request");
+    api_state
+        .scanner
+        .scan(query.runner_set.as_deref())
+        .await
+        .map(Json)
+        .map_err(|e| error_response(StatusCode::BAD_GATEWAY, format!("{:#}", e)))
+}
+
+async fn get_uptime(
+    State(api_state): State<StatusApiState>,
+    Query(query): Query<UptimeQuery>,
+) -> Result<Json<UptimeReport>, Response> {
+    let cfg = api_state.scanner.cfg();
+    let history_path = cfg.history_path.as_ref().ok_or_else(|| {
+        error_response(
+            StatusCode::NOT_FOUND,
+            String::from("uptime reports need history_path to be configured"),
+        )
+    })?;
+    let (from, to) = parse_report_window(query.from.as_deref(), query.to.as_deref())
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    // the history file can be large, don't block the runtime
+    let history_path = history_path.clone();
+    tokio::task::spawn_blocking(move || compute_uptime(&load_history(&history_path)?, from, to))
+        .await
+        .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
@@ -1,8 +1,21 @@
-use reqwest::Client;
-use serde::Serialize;
-use std::{collections::HashMap, time::Duration};
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::RwLock;
 
-#[derive(Debug)]
+use crate::cloudevents::CloudEventsMode;
+use crate::event_sink::EventSinkConfig;
+use crate::secrets::REDACTED;
+use crate::template::TemplateFormat;
+
+// Debug is implemented by hand to redact secrets
 pub struct Config {
     pub runner_sets: Vec<RunnerSetConfig>,
     // this is used before constructing the config
@@ -11,10 +24,17 @@ pub struct Config {
     pub inbound_timeout: Duration,
 
     // templates for inbound request
-    pub created_template_path: String,
-    pub removed_template_path: String,
-    pub online_template_path: String,
-    pub offline_template_path: String,
+    // each one is optional when it is found in the template_dir
+    pub created_template_path: Option<String>,
+    pub removed_template_path: Option<String>,
+    pub online_template_path: Option<String>,
+    pub offline_template_path: Option<String>,
+    // templates that can be overridden per runner set
+    pub template_dir: Option<String>,
+    // the built-in templates used when nothing else is found
+    pub format: Option<TemplateFormat>,
+    // give the alert templates the Runner structs besides the event
+    pub legacy_template_fields: bool,
 
     // The grace period does not affect the created and removed state changes.
     // It only allows runners to briefly go offline and come back
@@ -25,19 +45,121 @@ pub struct Config {
     pub grace_period: u32,
     // used for testing
     pub allow_http: bool,
+    // render alerts and reports to stdout instead of sending them
+    pub dry_run: bool,
+
+    // append every state transition to this file when set
+    pub history_path: Option<String>,
+    // write every delivered alert as an event to these
+    pub event_sinks: Vec<EventSinkConfig>,
+    // send a report with availability statistics to every webhook
+    pub report_interval: Option<ReportInterval>,
+    pub report_template_path: Option<String>,
+    // serve the current state as JSON when set
+    pub status_api_address: Option<SocketAddr>,
+    // allow triggering scans through the status api with this bearer token
+    pub scan_api_token: Option<String>,
 }
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportInterval {
+    Daily,
+    Weekly,
+}
+
 // a runner set is all the runners that belong to a repo, org or enterprise
 // one runner set will be queried per github request
-#[derive(Debug)]
 pub struct RunnerSetConfig {
     pub name: String,
+    pub kind: RunnerSetKind,
+    // the org, repo or enterprise name, e.g. my-org/my-repo
+    pub slug: String,
+    // as configured, e.g. https://api.github.com
+    pub github_base_uri: String,
     pub github_endpoint: String,
     pub webhook_endpoint: String,
+    pub webhook: WebhookOptions,
+    // the format of the runner set or its notifier; takes precedence over the global templates
+    pub format: Option<TemplateFormat>,
+    // what sends the alerts to the webhook_endpoint
+    pub notifier: Notifier,
+    // the subject, topic or stream of the message broker event sinks
+    pub event_topic: Option<String>,
+    pub webhook_client: Client,
     pub github_client: Client,
 }
 
+#[derive(Debug, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum RunnerSetKind {
+    Org,
+    Repo,
+    Enterprise,
+}
+
+// the alert handler used for a runner set
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Notifier {
+    // the templated webhook
+    #[default]
+    Webhook,
+    Discord,
+    Mattermost,
+    Alertmanager,
+    Opsgenie,
+}
+
+impl RunnerSetKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunnerSetKind::Org => "org",
+            RunnerSetKind::Repo => "repo",
+            RunnerSetKind::Enterprise => "enterprise",
+        }
+    }
+}
+
+// how the webhook of a runner set is called
+#[derive(Clone, PartialEq)]
+pub struct WebhookOptions {
+    pub method: Method,
+    pub content_type: Option<String>,
+    // the values are Tera templates with the same context as the message body
+    pub headers: Vec<(String, String)>,
+    pub auth: Option<WebhookAuth>,
+    // every request is signed with this shared secret when set
+    pub signing_secret: Option<String>,
+    // alerts are wrapped in a CloudEvent when set
+    pub cloudevents: Option<CloudEventsMode>,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum WebhookAuth {
+    Bearer(String),
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    // `Authorization: GenieKey <key>` for the Opsgenie API
+    GenieKey(String),
+}
+
+impl Default for WebhookOptions {
+    fn default() -> Self {
+        WebhookOptions {
+            method: Method::POST,
+            content_type: None,
+            headers: vec![],
+            auth: None,
+            signing_secret: None,
+            cloudevents: None,
+        }
+    }
+}
+
 // this runner struct will be serialized for the webhook message body
-#[derive(Debug, Serialize, PartialEq, Clone, Hash, Eq)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Hash, Eq)]
 pub struct Runner {
     pub utc_ping_time: String,
     // the state reflected in the inbound webhook events
@@ -46,6 +168,10 @@ pub struct Runner {
     // the state the GitHub API provides
     // it does not include the grace period
     pub online_for_github_api: bool,
+    // whether the runner is executing a job right now
+    // not part of the legacy template fields, whose payloads must not change
+    #[serde(skip_serializing, default)]
+    pub busy: bool,
     // for how long is interpret_online != online_for_github_api
     pub online_state_change_since: u32,
     pub runner_set: String,
@@ -55,11 +181,115 @@ pub struct Runner {
     pub labels: Vec<String>,
 
     // this contains a secret key
-    #[serde(skip_serializing)]
+    // it is taken from the config when a runner is deserialized
+    #[serde(skip_serializing, default)]
     pub webhook_endpoint: String,
+    // taken from the config like the webhook_endpoint, so the alerts of removed runner sets
+    // keep their envelope
+    #[serde(skip_serializing, default)]
+    pub webhook_cloudevents: Option<CloudEventsMode>,
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("runner_sets", &self.runner_sets)
+            .field("github_timeout", &self.github_timeout)
+            .field("inbound_timeout", &self.inbound_timeout)
+            .field("created_template_path", &self.created_template_path)
+            .field("removed_template_path", &self.removed_template_path)
+            .field("online_template_path", &self.online_template_path)
+            .field("offline_template_path", &self.offline_template_path)
+            .field("template_dir", &self.template_dir)
+            .field("format", &self.format)
+            .field("legacy_template_fields", &self.legacy_template_fields)
+            .field("grace_period", &self.grace_period)
+            .field("allow_http", &self.allow_http)
+            .field("dry_run", &self.dry_run)
+            .field("history_path", &self.history_path)
+            .field("event_sinks", &self.event_sinks)
+            .field("report_interval", &self.report_interval)
+            .field("report_template_path", &self.report_template_path)
+            .field("status_api_address", &self.status_api_address)
+            .field(
+                "scan_api_token",
+                &self.scan_api_token.as_ref().map(|_| REDACTED),
+            )
+            .finish()
+    }
+}
+
+// the client carries the PAT
+impl fmt::Debug for RunnerSetConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RunnerSetConfig")
+            .field("name", &self.name)
+            .field("github_endpoint", &self.github_endpoint)
+            .field("webhook_endpoint", &REDACTED)
+            .field("webhook", &self.webhook)
+            .field("format", &self.format)
+            .field("notifier", &self.notifier)
+            .field("event_topic", &self.event_topic)
+            .finish_non_exhaustive()
+    }
+}
+
+// header values and credentials are secrets
+impl fmt::Debug for WebhookOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header_names: Vec<&str> = self.headers.iter().map(|(name, _)| name.as_str()).collect();
+        f.debug_struct("WebhookOptions")
+            .field("method", &self.method)
+            .field("content_type", &self.content_type)
+            .field("headers", &header_names)
+            .field("auth", &self.auth)
+            .field("signed", &self.signing_secret.is_some())
+            .field("cloudevents", &self.cloudevents)
+            .finish()
+    }
 }
 
-#[derive(Debug, PartialEq, Hash, Eq)]
+impl fmt::Debug for WebhookAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookAuth::Bearer(_) => f.debug_tuple("Bearer").field(&REDACTED).finish(),
+            WebhookAuth::GenieKey(_) => f.debug_tuple("GenieKey").field(&REDACTED).finish(),
+            WebhookAuth::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &REDACTED)
+                .finish(),
+        }
+    }
+}
+
+impl Runner {
+    // offline once the grace period has passed; these runners have a firing alert
+    pub fn is_offline(&self) -> bool {
+        self.interpret_online == Some(false)
+    }
+}
+
+impl fmt::Debug for Runner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Runner")
+            .field("utc_ping_time", &self.utc_ping_time)
+            .field("interpret_online", &self.interpret_online)
+            .field("online_for_github_api", &self.online_for_github_api)
+            .field("busy", &self.busy)
+            .field("online_state_change_since", &self.online_state_change_since)
+            .field("runner_set", &self.runner_set)
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("os", &self.os)
+            .field("labels", &self.labels)
+            .field("webhook_endpoint", &REDACTED)
+            .field("webhook_cloudevents", &self.webhook_cloudevents)
+            .finish()
+    }
+}
+
+#[derive(Debug, PartialEq, Hash, Eq, Clone)]
 pub enum RunnerStateChange {
     // a new runner just popped up
     Created(Runner),
@@ -74,3 +304,142 @@ pub enum RunnerStateChange {
 }
 
 pub type RunnerMap = HashMap<String, Runner>;
+
+impl RunnerStateChange {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RunnerStateChange::Created(_) => "created",
+            RunnerStateChange::Removed(_) => "removed",
+            RunnerStateChange::Offline(_, _) => "offline",
+            RunnerStateChange::Online(_, _) => "online",
+        }
+    }
+
+    // the most recent known version of the runner
+    pub fn runner(&self) -> &Runner {
+        match self {
+            RunnerStateChange::Created(new_runner) => new_runner,
+            RunnerStateChange::Removed(old_runner) => old_runner,
+            RunnerStateChange::Offline(_, new_runner) => new_runner,
+            RunnerStateChange::Online(_, new_runner) => new_runner,
+        }
+    }
+}
+
+// an alert that has been successfully sent
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct DeliveredAlert {
+    pub utc_time: String,
+    pub kind: String,
+    pub runner_set: String,
+    pub runner_id: i64,
+    pub runner_name: String,
+    pub labels: Vec<String>,
+    // the interpreted online state after the change; None when the runner has been removed
+    pub online: Option<bool>,
+}
+
+impl DeliveredAlert {
+    pub fn new(change: &RunnerStateChange) -> Self {
+        let runner = change.runner();
+        DeliveredAlert {
+            utc_time: Utc::now().to_rfc3339(),
+            kind: change.kind().to_string(),
+            runner_set: runner.runner_set.clone(),
+            runner_id: runner.id,
+            runner_name: runner.name.clone(),
+            labels: runner.labels.clone(),
+            online: match change {
+                RunnerStateChange::Removed(_) => None,
+                _ => runner.interpret_online,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone, Default)]
+pub struct RunnerSetStatus {
+    pub last_scan_time: Option<String>,
+    pub last_alert: Option<DeliveredAlert>,
+}
+
+// everything the monitor knows at the moment
+// this is shared between the scan loop and the status api
+#[derive(Debug, Default)]
+pub struct MonitorState {
+    pub runners: RunnerMap,
+    // runner key -> since when the runner is in its current interpreted online state
+    pub state_since: HashMap<String, DateTime<Utc>>,
+    // runner set name -> status
+    pub runner_sets: HashMap<String, RunnerSetStatus>,
+    // oldest first
+    pub recent_alerts: VecDeque<DeliveredAlert>,
+}
+
+impl MonitorState {
+    // how many alerts are kept in recent_alerts
+    const RECENT_ALERTS_LEN: usize = 50;
+
+    pub fn new(cfg: &Config, runners: RunnerMap) -> Self {
+        let mut state = MonitorState::default();
+        // loading the config performs the first scan
+        let runner_sets = cfg.runner_sets.iter().collect::<Vec<_>>();
+        state.record_scan(&runner_sets);
+        state.update_runners(&runner_sets, runners);
+        state
+    }
+
+    pub fn record_scan(&mut self, runner_sets: &[&RunnerSetConfig]) {
+        let now = Utc::now().to_rfc3339();
+        for runner_set in runner_sets {
+            self.runner_sets
+                .entry(runner_set.name.clone())
+                .or_default()
+                .last_scan_time = Some(now.clone());
+        }
+    }
+
+    pub fn record_alerts(&mut self, alerts: &[DeliveredAlert]) {
+        for alert in alerts {
+            self.runner_sets
+                .entry(alert.runner_set.clone())
+                .or_default()
+                .last_alert = Some(alert.clone());
+            self.recent_alerts.push_back(alert.clone());
+        }
+        while self.recent_alerts.len() > Self::RECENT_ALERTS_LEN {
+            self.recent_alerts.pop_front();
+        }
+    }
+
+    // forget everything about these runner sets
+    pub fn remove_runner_sets(&mut self, runner_set_names: &[String]) {
+        self.runners
+            .retain(|_, r| !runner_set_names.contains(&r.runner_set));
+        self.state_since
+            .retain(|key, _| self.runners.contains_key(key));
+        self.runner_sets
+            .retain(|name, _| !runner_set_names.contains(name));
+    }
+
+    // replace all runners of the scanned runner sets
+    pub fn update_runners(&mut self, runner_sets: &[&RunnerSetConfig], new_runners: RunnerMap) {
+        let now = Utc::now();
+        for (key, new_runner) in &new_runners {
+            let unchanged = self
+                .runners
+                .get(key)
+                .is_some_and(|old| old.interpret_online == new_runner.interpret_online);
+            if !unchanged || !self.state_since.contains_key(key) {
+                self.state_since.insert(key.clone(), now);
+            }
+        }
+        self.runners
+            .retain(|_, r| !runner_sets.iter().any(|s| s.name == r.runner_set));
+        self.runners.extend(new_runners);
+        self.state_since
+            .retain(|key, _| self.runners.contains_key(key));
+    }
+}
+
+pub type SharedState = Arc<RwLock<MonitorState>>;
@@ -0,0 +1,189 @@
+use anyhow::Result;
+use opentelemetry::{
+    global,
+    propagation::Injector,
+    trace::{TraceContextExt, TracerProvider as _},
+    KeyValue,
+};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    metrics::{PeriodicReader, SdkMeterProvider},
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{Tracer, TracerProvider},
+    Resource,
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::structs::{MonitorState, RunnerSetConfig};
+
+const SERVICE_NAME: &str = "gh_runner_uptime";
+
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OtelProtocol {
+    #[default]
+    Grpc,
+    // protobuf over http
+    Http,
+}
+
+// the otel section of the config file
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct OtelConfig {
+    // e.g. http://localhost:4317 for grpc or http://localhost:4318 for http
+    pub endpoint: String,
+    #[serde(default)]
+    pub protocol: OtelProtocol,
+    #[serde(default = "default_metrics_interval_seconds")]
+    pub metrics_interval_seconds: u64,
+}
+fn default_metrics_interval_seconds() -> u64 {
+    60
+}
+
+// keeps the exporters running; shut down to flush everything before exiting
+pub struct Telemetry {
+    tracer_provider: TracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Telemetry {
+    // exports all spans
+    pub fn layer<S>(&self) -> OpenTelemetryLayer<S, Tracer>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.tracer_provider.tracer(SERVICE_NAME))
+    }
+
+    // logging might be gone already
+    pub fn shutdown(&self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            eprintln!("Unable to flush the spans: {}", e);
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            eprintln!("Unable to flush the metrics: {}", e);
+        }
+    }
+}
+
+// needs to be called from within the tokio runtime
+pub fn init_telemetry(cfg: &OtelConfig) -> Result<Telemetry> {
+    let resource = Resource::new([
+        KeyValue::new("service.name", SERVICE_NAME),
+        KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+    ]);
+    let endpoint = cfg.endpoint.trim_end_matches('/');
+    let (span_exporter, metric_exporter) = match cfg.protocol {
+        OtelProtocol::Grpc => (
+            SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build()?,
+            MetricExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build()?,
+        ),
+        OtelProtocol::Http => (
+            SpanExporter::builder()
+                .with_http()
+                .with_endpoint(format!("{}/v1/traces", endpoint))
+                .build()?,
+            MetricExporter::builder()
+                .with_http()
+                .with_endpoint(format!("{}/v1/metrics", endpoint))
+                .build()?,
+        ),
+    };
+    let tracer_provider = TracerProvider::builder()
+        .with_batch_exporter(span_exporter, runtime::Tokio)
+        .with_resource(resource.clone())
+        .build();
+    let reader = PeriodicReader::builder(metric_exporter, runtime::Tokio)
+        .with_interval(Duration::from_secs(cfg.metrics_interval_seconds))
+        .build();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader)
+        .with_resource(resource)
+        .build();
+    global::set_meter_provider(meter_provider.clone());
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(Telemetry {
+        tracer_provider,
+        meter_provider,
+    })
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+// the traceparent header of the current span; empty when telemetry is disabled
+pub fn trace_context_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let context = Span::current().context();
+    if context.span().span_context().is_valid() {
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut HeaderInjector(&mut headers))
+        });
+    }
+    headers
+}
+
+// the instruments are looked up on every call to always use the current meter provider
+pub fn record_alert(kind: &str, runner_set: &str, delivered: bool) {
+    global::meter(SERVICE_NAME)
+        .u64_counter("gh_runner_uptime.alerts")
+        .with_description("alerts by kind and whether they got delivered")
+        .build()
+        .add(
+            1,
+            &[
+                KeyValue::new("kind", kind.to_string()),
+                KeyValue::new("runner_set", runner_set.to_string()),
+                KeyValue::new("outcome", if delivered { "delivered" } else { "failed" }),
+            ],
+        );
+}
+
+pub fn record_runner_counts(state: &MonitorState, runner_sets: &[&RunnerSetConfig]) {
+    let gauge = global::meter(SERVICE_NAME)
+        .u64_gauge("gh_runner_uptime.runners")
+        .with_description("runners by runner set and interpreted state")
+        .build();
+    for runner_set in runner_sets {
+        let runners = || {
+            state
+                .runners
+                .values()
+                .filter(|r| r.runner_set == runner_set.name)
+        };
+        for (status, online) in [("online", true), ("offline", false)] {
+            gauge.record(
+                runners()
+                    .filter(|r| r.interpret_online == Some(online))
+                    .count() as u64,
+                &[
+                    KeyValue::new("runner_set", runner_set.name.clone()),
+                    KeyValue::new("status", status),
+                ],
+            );
+        }
+    }
+}
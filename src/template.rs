@@ -0,0 +1,333 @@
+use anyhow::{bail, ensure, Context};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, sync::LazyLock};
+use tera::{to_value, try_get_value, Result, Tera, Value};
+
+use crate::{
+    dashboard::format_duration,
+    structs::{Config, Notifier},
+};
+
+// who sent an alert, available as `monitor` in every template
+#[derive(Debug, Serialize, Clone)]
+pub struct MonitorInfo {
+    pub version: &'static str,
+    pub hostname: String,
+}
+
+pub static MONITOR_INFO: LazyLock<MonitorInfo> = LazyLock::new(|| MonitorInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    hostname: hostname(),
+});
+
+// the container id in docker
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+pub fn register_filters(tera: &mut Tera) {
+    tera.register_filter("json_string", json_string);
+    tera.register_filter("humanize_duration", humanize_duration);
+    tera.register_filter("markdown_escape", markdown_escape);
+    tera.register_filter("slack_escape", slack_escape);
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+// the value escaped for the inside of a JSON string, e.g. `"text": "{{ x | json_string }}"`
+fn json_string(value: &Value, _: &HashMap<String, Value>) -> Result<Value> {
+    let quoted = serde_json::to_string(&as_text(value))?;
+    Ok(to_value(&quoted[1..quoted.len() - 1])?)
+}
+
+// seconds like the dashboard shows durations, e.g. `2d 3h`, `3h 25m` or `42s`
+fn humanize_duration(value: &Value, _: &HashMap<String, Value>) -> Result<Value> {
+    let seconds = try_get_value!("humanize_duration", "value", i64, value);
+    let duration = Duration::try_seconds(seconds)
+        .ok_or_else(|| tera::Error::msg(format!("{} seconds is out of range", seconds)))?;
+    let sign = if seconds < 0 { "-" } else { "" };
+    Ok(to_value(format!(
+        "{}{}",
+        sign,
+        format_duration(duration.abs())
+    ))?)
+}
+
+fn markdown_escape(value: &Value, _: &HashMap<String, Value>) -> Result<Value> {
+    Ok(to_value(escape_markdown(&as_text(value)))?)
+}
+
+// CommonMark punctuation that could start or end formatting
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "\\`*_{}[]()<>#+-.!|~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// https://api.slack.com/reference/surfaces/formatting#escaping
+fn slack_escape(value: &Value, _: &HashMap<String, Value>) -> Result<Value> {
+    let escaped = as_text(value)
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    Ok(to_value(escaped)?)
+}
+
+pub const CREATED: &str = "created";
+pub const REMOVED: &str = "removed";
+pub const ONLINE: &str = "online";
+pub const OFFLINE: &str = "offline";
+pub const REPORT: &str = "report";
+const ALERT_KINDS: [&str; 4] = [CREATED, REMOVED, ONLINE, OFFLINE];
+
+// the built-in templates; each one renders every kind of alert and the report
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TemplateFormat {
+    GenericJson,
+    Slack,
+    Teams,
+    Discord,
+    Mattermost,
+    PlainText,
+}
+
+impl TemplateFormat {
+    pub const ALL: [TemplateFormat; 6] = [
+        TemplateFormat::GenericJson,
+        TemplateFormat::Slack,
+        TemplateFormat::Teams,
+        TemplateFormat::Discord,
+        TemplateFormat::Mattermost,
+        TemplateFormat::PlainText,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TemplateFormat::GenericJson => "generic-json",
+            TemplateFormat::Slack => "slack",
+            TemplateFormat::Teams => "teams",
+            TemplateFormat::Discord => "discord",
+            TemplateFormat::Mattermost => "mattermost",
+            TemplateFormat::PlainText => "plain-text",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TemplateFormat::PlainText => "text/plain; charset=utf-8",
+            _ => "application/json",
+        }
+    }
+
+    fn source(&self) -> &'static str {
+        match self {
+            TemplateFormat::GenericJson => include_str!("templates/generic-json.json.j2"),
+            TemplateFormat::Slack => include_str!("templates/slack.json.j2"),
+            TemplateFormat::Teams => include_str!("templates/teams.json.j2"),
+            TemplateFormat::Discord => include_str!("templates/discord.json.j2"),
+            TemplateFormat::Mattermost => include_str!("templates/mattermost.json.j2"),
+            TemplateFormat::PlainText => include_str!("templates/plain-text.txt.j2"),
+        }
+    }
+
+    fn template_name(&self) -> String {
+        format!("builtin:{}", self.as_str())
+    }
+}
+
+// included by the built-in templates
+const BUILTIN_PARTIALS: [(&str, &str); 5] = [
+    ("builtin:macros", include_str!("templates/macros.txt.j2")),
+    ("builtin:title", include_str!("templates/title.txt.j2")),
+    ("builtin:text", include_str!("templates/text.txt.j2")),
+    ("builtin:color", include_str!("templates/color.txt.j2")),
+    ("builtin:footer", include_str!("templates/footer.txt.j2")),
+];
+
+// All templates of a config, compiled once per config load.
+// Every kind is looked up in this order: `<template_dir>/<slug>/<kind>.j2`, the `format` of the
+// runner set, `<template_dir>/<kind>.j2`, `<kind>_template_path`, the global `format`
+pub struct Templates {
+    tera: Tera,
+    // runner set name -> kind -> template name
+    per_set: HashMap<String, HashMap<&'static str, String>>,
+    // for runners of a runner set that has been removed from the config
+    fallback: HashMap<&'static str, String>,
+}
+
+impl Templates {
+    pub fn new(cfg: &Config) -> anyhow::Result<Self> {
+        let mut tera = match &cfg.template_dir {
+            Some(template_dir) => {
+                ensure!(
+                    Path::new(template_dir).is_dir(),
+                    "template_dir {} is not a directory",
+                    template_dir
+                );
+                // all files are compiled together, so they can extend and include each other
+                Tera::new(&format!("{}/**/*", template_dir.trim_end_matches('/')))?
+            }
+            None => Tera::default(),
+        };
+        register_filters(&mut tera);
+        let builtins = TemplateFormat::ALL
+            .iter()
+            .map(|format| (format.template_name(), format.source()));
+        tera.add_raw_templates(
+            BUILTIN_PARTIALS
+                .iter()
+                .map(|(name, source)| (name.to_string(), *source))
+                .chain(builtins),
+        )?;
+        for (kind, path) in [
+            (CREATED, &cfg.created_template_path),
+            (REMOVED, &cfg.removed_template_path),
+            (ONLINE, &cfg.online_template_path),
+            (OFFLINE, &cfg.offline_template_path),
+            (REPORT, &cfg.report_template_path),
+        ] {
+            if let Some(path) = path {
+                tera.add_template_file(path, Some(kind))?;
+            }
+        }
+        // the header templates are compiled with the others to get the same filters
+        for runner_set in &cfg.runner_sets {
+            for (name, value) in &runner_set.webhook.headers {
+                tera.add_raw_template(&header_template_name(&runner_set.name, name), value)
+                    .with_context(|| {
+                        format!(
+                            "Failed to compile the webhook header {} of {}",
+                            name, runner_set.name
+                        )
+                    })?;
+            }
+        }
+
+        // None for the runners of removed runner sets, which need no templates
+        let lookup_all = |slug: Option<&str>,
+                          set_format: Option<TemplateFormat>,
+                          name: &str,
+                          notifier: Option<Notifier>|
+         -> anyhow::Result<_> {
+            let required = |kind: &str| match notifier {
+                Some(notifier) if kind == REPORT => {
+                    notifier.sends_reports() && cfg.report_interval.is_some()
+                }
+                Some(notifier) => notifier.uses_templates(),
+                None => false,
+            };
+            let mut found = HashMap::new();
+            for kind in ALERT_KINDS.into_iter().chain([REPORT]) {
+                match lookup(&tera, slug, kind, set_format, cfg.format) {
+                    Some(template) => {
+                        found.insert(kind, template);
+                    }
+                    None if !required(kind) => (),
+                    None => bail!(
+                        "No {} template found for {}; set format or {}_template_path, or add {}.j2 to the template_dir",
+                        kind,
+                        name,
+                        kind,
+                        kind
+                    ),
+                }
+            }
+            Ok(found)
+        };
+        let per_set = cfg
+            .runner_sets
+            .iter()
+            .map(|s| {
+                let templates = lookup_all(Some(&s.slug), s.format, &s.name, Some(s.notifier))?;
+                Ok((s.name.clone(), templates))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        // a template_dir with per-set templates only is fine
+        let fallback = lookup_all(None, None, "removed runner sets", None)?;
+        Ok(Templates {
+            tera,
+            per_set,
+            fallback,
+        })
+    }
+
+    fn template_name(&self, runner_set: &str, kind: &str) -> Option<&str> {
+        self.per_set
+            .get(runner_set)
+            .unwrap_or(&self.fallback)
+            .get(kind)
+            .map(String::as_str)
+    }
+
+    pub fn has_template(&self, runner_set: &str, kind: &str) -> bool {
+        self.template_name(runner_set, kind).is_some()
+    }
+
+    pub fn render(
+        &self,
+        runner_set: &str,
+        kind: &str,
+        ctx: &tera::Context,
+    ) -> anyhow::Result<String> {
+        let template = self
+            .template_name(runner_set, kind)
+            .with_context(|| format!("No {} template found for {}", kind, runner_set))?;
+        self.tera
+            .render(template, ctx)
+            .with_context(|| format!("Failed to render the {} template", template))
+    }
+
+    pub fn render_header(
+        &self,
+        runner_set: &str,
+        header: &str,
+        ctx: &tera::Context,
+    ) -> anyhow::Result<String> {
+        self.tera
+            .render(&header_template_name(runner_set, header), ctx)
+            .with_context(|| format!("Failed to render the webhook header {}", header))
+    }
+}
+
+fn lookup(
+    tera: &Tera,
+    slug: Option<&str>,
+    kind: &str,
+    set_format: Option<TemplateFormat>,
+    global_format: Option<TemplateFormat>,
+) -> Option<String> {
+    let exists = |name: &String| tera.get_template_names().any(|n| n == name);
+    let per_set = slug
+        .map(|slug| format!("{}/{}.j2", slug, kind))
+        .filter(exists)
+        .or_else(|| set_format.map(|format| format.template_name()));
+    // the *_template_path templates are named after their kind
+    per_set
+        .or_else(|| {
+            [format!("{}.j2", kind), kind.to_string()]
+                .into_iter()
+                .find(exists)
+        })
+        .or_else(|| global_format.map(|format| format.template_name()))
+}
+
+fn header_template_name(runner_set: &str, header: &str) -> String {
+    format!("header {} of {}", header, runner_set)
+}
@@ -5,6 +5,7 @@ use tokio::net::TcpListener;
 use tokio::time::sleep;
 
 use crate::inbound_alert_handler::InboundAlertHandler;
+use crate::test_fixtures::test_cfg_with_templates;
 use crate::{
     alert::alert_all_changes_and_update_grace_period,
     github::key_runner,
@@ -29,6 +30,7 @@ async fn alert_grace_0_test() {
             // the initial interpret_online needs to be set
             interpret_online: Some(true),
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 69,
@@ -36,12 +38,14 @@ async fn alert_grace_0_test() {
             os: String::from("linux"),
             labels: vec![String::from("some label")],
             webhook_endpoint: inbound_address_http.clone(),
+            webhook_cloudevents: None,
         })]),
         // runner went offline
         RunnerMap::from([key_runner(Runner {
             utc_ping_time: String::from("2"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 69,
@@ -49,6 +53,7 @@ async fn alert_grace_0_test() {
             os: String::from("linux"),
             labels: vec![String::from("some label")],
             webhook_endpoint: inbound_address_http.clone(),
+            webhook_cloudevents: None,
         })]),
         // runner removed
         RunnerMap::from([]),
@@ -58,6 +63,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("3"),
                 interpret_online: None,
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -67,11 +73,13 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: inbound_address_http.clone(),
+                webhook_cloudevents: None,
             }),
             key_runner(Runner {
                 utc_ping_time: String::from("3"),
                 interpret_online: None,
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -81,6 +89,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: inbound_address_http.clone(),
+                webhook_cloudevents: None,
             }),
         ]),
         // nothing changed
@@ -89,6 +98,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("4"),
                 interpret_online: None,
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -100,11 +110,13 @@ async fn alert_grace_0_test() {
                 // label changes don't cause an alert
                 labels: vec![String::from("some other label")],
                 webhook_endpoint: inbound_address_http.clone(),
+                webhook_cloudevents: None,
             }),
             key_runner(Runner {
                 utc_ping_time: String::from("4"),
                 interpret_online: None,
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -115,6 +127,7 @@ async fn alert_grace_0_test() {
                 // label changes don't cause an alert
                 labels: vec![String::from("some other label")],
                 webhook_endpoint: inbound_address_http.clone(),
+                webhook_cloudevents: None,
             }),
         ]),
         // one runner went offline
@@ -123,6 +136,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("5"),
                 interpret_online: None,
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -132,11 +146,13 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: inbound_address_http.clone(),
+                webhook_cloudevents: None,
             }),
             key_runner(Runner {
                 utc_ping_time: String::from("5"),
                 interpret_online: None,
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -146,6 +162,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: inbound_address_http.clone(),
+                webhook_cloudevents: None,
             }),
         ]),
         // other runner came online
@@ -154,6 +171,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("6"),
                 interpret_online: None,
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -163,11 +181,13 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: inbound_address_http.clone(),
+                webhook_cloudevents: None,
             }),
             key_runner(Runner {
                 utc_ping_time: String::from("6"),
                 interpret_online: None,
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -177,6 +197,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: inbound_address_http.clone(),
+                webhook_cloudevents: None,
             }),
         ]),
     ];
@@ -185,6 +206,7 @@ async fn alert_grace_0_test() {
             utc_ping_time: String::from("6"),
             interpret_online: Some(false),
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisOtherTestOrg; github: https://github.com/api/v3"),
             id: 420,
@@ -192,11 +214,13 @@ async fn alert_grace_0_test() {
             os: String::from("linux"),
             labels: vec![String::from("some label")],
             webhook_endpoint: inbound_address_http.clone(),
+            webhook_cloudevents: None,
         }),
         key_runner(Runner {
             utc_ping_time: String::from("6"),
             interpret_online: Some(true),
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisOtherTestOrg; github: https://github.com/api/v3"),
             id: 12,
@@ -204,6 +228,7 @@ async fn alert_grace_0_test() {
             os: String::from("linux"),
             labels: vec![String::from("some label")],
             webhook_endpoint: inbound_address_http.clone(),
+            webhook_cloudevents: None,
         }),
     ]);
     let awaited_messages = HashSet::from([
@@ -262,15 +287,11 @@ async fn alert_test_uptime(
     let mut states = states.into_iter();
     // only used for send_alert
     let cfg = Config {
-        runner_sets: vec![],
         github_timeout: Duration::from_millis(30),
         inbound_timeout: Duration::from_millis(30),
         grace_period,
         allow_http: true,
-        created_template_path: String::from("src/tests/test_created_template.txt.j2"),
-        removed_template_path: String::from("src/tests/test_removed_template.txt.j2"),
-        online_template_path: String::from("src/tests/test_online_template.txt.j2"),
-        offline_template_path: String::from("src/tests/test_offline_template.txt.j2"),
+        ..test_cfg_with_templates()
     };
 
     let mut runners = states.next().unwrap();
@@ -315,8 +336,8 @@ async fn inbound_mock(address: &str, expected_messages: HashSet<&str>) {
     while !expected_payloads.is_empty() {
         let mut stream = listener.accept().await.unwrap().0;
         let mut buf: Vec<u8> = vec![0; 4000];
-        stream.read(&mut buf).await.unwrap();
-        let payload = String::from_utf8(buf).unwrap();
+        let len = stream.read(&mut buf).await.unwrap();
+        let payload = String::from_utf8(buf[..len].to_vec()).unwrap();
         let payload = payload.trim_matches(char::from(0));
         println!(
             "\n### begin received payload ###\n{}\n### end payload ###\n",
@@ -325,7 +346,7 @@ async fn inbound_mock(address: &str, expected_messages: HashSet<&str>) {
         assert!(expected_payloads.remove(payload));
 
         let response = "HTTP/1.1 200 OK\r\n\r\n";
-        stream.write(response.as_bytes()).await.unwrap();
+        stream.write_all(response.as_bytes()).await.unwrap();
     }
     println!("stopping mock inbound server");
 }
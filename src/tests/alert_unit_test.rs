@@ -3,6 +3,7 @@ use std::time::Duration;
 
 use crate::structs::RunnerStateChange;
 use crate::test_alert_handler::TestAlertHandler;
+use crate::test_fixtures::test_cfg;
 use crate::{
     alert::alert_all_changes_and_update_grace_period,
     github::key_runner,
@@ -15,15 +16,10 @@ use crate::{
 async fn alert_grace_0_should_panic_test() {
     let grace_period = 0;
     let cfg = Config {
-        runner_sets: vec![],
         github_timeout: Duration::from_millis(30),
         inbound_timeout: Duration::from_millis(30),
         grace_period,
-        allow_http: false,
-        created_template_path: String::from(""),
-        removed_template_path: String::from(""),
-        online_template_path: String::from(""),
-        offline_template_path: String::from(""),
+        ..test_cfg()
     };
 
     // initial setup
@@ -32,6 +28,7 @@ async fn alert_grace_0_should_panic_test() {
         // the initial interpret_online needs to be set
         interpret_online: Some(true),
         online_for_github_api: true,
+        busy: false,
         online_state_change_since: 0,
         runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
         id: 69,
@@ -39,6 +36,7 @@ async fn alert_grace_0_should_panic_test() {
         os: String::from("linux"),
         labels: vec![String::from("some label")],
         webhook_endpoint: String::from("https://chris-besch.com"),
+        webhook_cloudevents: None,
     })]);
 
     // runner went offline
@@ -49,6 +47,7 @@ async fn alert_grace_0_should_panic_test() {
             utc_ping_time: String::from("2"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 69,
@@ -56,12 +55,14 @@ async fn alert_grace_0_should_panic_test() {
             os: String::from("linux"),
             labels: vec![String::from("some label")],
             webhook_endpoint: String::from("https://chris-besch.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([RunnerStateChange::Offline(
             Runner {
                 utc_ping_time: String::from("1"),
                 interpret_online: Some(true),
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
                 id: 69,
@@ -69,12 +70,14 @@ async fn alert_grace_0_should_panic_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             },
             Runner {
                 utc_ping_time: String::from("2"),
                 interpret_online: Some(false),
                 // this should be false and thus causes a panic
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
                 id: 69,
@@ -82,6 +85,7 @@ async fn alert_grace_0_should_panic_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             },
         )]),
     )
@@ -92,15 +96,10 @@ async fn alert_grace_0_should_panic_test() {
 async fn alert_grace_0_test() {
     let grace_period = 0;
     let cfg = Config {
-        runner_sets: vec![],
         github_timeout: Duration::from_millis(30),
         inbound_timeout: Duration::from_millis(30),
         grace_period,
-        allow_http: false,
-        created_template_path: String::from(""),
-        removed_template_path: String::from(""),
-        online_template_path: String::from(""),
-        offline_template_path: String::from(""),
+        ..test_cfg()
     };
 
     // initial setup
@@ -109,6 +108,7 @@ async fn alert_grace_0_test() {
         // the initial interpret_online needs to be set
         interpret_online: Some(true),
         online_for_github_api: true,
+        busy: false,
         online_state_change_since: 0,
         runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
         id: 69,
@@ -116,6 +116,7 @@ async fn alert_grace_0_test() {
         os: String::from("linux"),
         labels: vec![String::from("some label")],
         webhook_endpoint: String::from("https://chris-besch.com"),
+        webhook_cloudevents: None,
     })]);
 
     // runner went offline
@@ -126,6 +127,7 @@ async fn alert_grace_0_test() {
             utc_ping_time: String::from("2"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 69,
@@ -133,12 +135,14 @@ async fn alert_grace_0_test() {
             os: String::from("linux"),
             labels: vec![String::from("some label")],
             webhook_endpoint: String::from("https://chris-besch.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([RunnerStateChange::Offline(
             Runner {
                 utc_ping_time: String::from("1"),
                 interpret_online: Some(true),
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
                 id: 69,
@@ -146,11 +150,13 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             },
             Runner {
                 utc_ping_time: String::from("2"),
                 interpret_online: Some(false),
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
                 id: 69,
@@ -158,6 +164,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             },
         )]),
     )
@@ -172,6 +179,7 @@ async fn alert_grace_0_test() {
             utc_ping_time: String::from("2"),
             interpret_online: Some(false),
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 69,
@@ -179,6 +187,7 @@ async fn alert_grace_0_test() {
             os: String::from("linux"),
             labels: vec![String::from("some label")],
             webhook_endpoint: String::from("https://chris-besch.com"),
+            webhook_cloudevents: None,
         })]),
     )
     .await;
@@ -198,6 +207,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("3"),
                 interpret_online: None,
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -207,11 +217,13 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
             key_runner(Runner {
                 utc_ping_time: String::from("3"),
                 interpret_online: None,
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -221,6 +233,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
         ]),
         HashSet::from([
@@ -228,6 +241,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("3"),
                 interpret_online: Some(false),
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -237,11 +251,13 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
             RunnerStateChange::Created(Runner {
                 utc_ping_time: String::from("3"),
                 interpret_online: Some(true),
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -251,6 +267,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
         ]),
     )
@@ -265,6 +282,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("4"),
                 interpret_online: None,
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -276,11 +294,13 @@ async fn alert_grace_0_test() {
                 // label changes don't cause an alert
                 labels: vec![String::from("some other label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
             key_runner(Runner {
                 utc_ping_time: String::from("4"),
                 interpret_online: None,
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -290,6 +310,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
         ]),
         HashSet::from([]),
@@ -305,6 +326,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("5"),
                 interpret_online: None,
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -314,11 +336,13 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
             key_runner(Runner {
                 utc_ping_time: String::from("5"),
                 interpret_online: None,
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -328,6 +352,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
         ]),
         HashSet::from([RunnerStateChange::Offline(
@@ -335,6 +360,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("4"),
                 interpret_online: Some(true),
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -346,11 +372,13 @@ async fn alert_grace_0_test() {
                 // label changes don't cause an alert
                 labels: vec![String::from("some other label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             },
             Runner {
                 utc_ping_time: String::from("5"),
                 interpret_online: Some(false),
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -360,6 +388,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             },
         )]),
     )
@@ -374,6 +403,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("6"),
                 interpret_online: None,
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -383,11 +413,13 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
             key_runner(Runner {
                 utc_ping_time: String::from("6"),
                 interpret_online: None,
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -397,6 +429,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
         ]),
         HashSet::from([RunnerStateChange::Online(
@@ -404,6 +437,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("5"),
                 interpret_online: Some(false),
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -413,11 +447,13 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             },
             Runner {
                 utc_ping_time: String::from("6"),
                 interpret_online: Some(true),
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3",
@@ -427,6 +463,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             },
         )]),
     )
@@ -439,6 +476,7 @@ async fn alert_grace_0_test() {
                 utc_ping_time: String::from("6"),
                 interpret_online: Some(false),
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3"
@@ -448,11 +486,13 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
             key_runner(Runner {
                 utc_ping_time: String::from("6"),
                 interpret_online: Some(true),
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from(
                     "org: chrisOtherTestOrg; github: https://github.com/api/v3"
@@ -462,6 +502,7 @@ async fn alert_grace_0_test() {
                 os: String::from("linux"),
                 labels: vec![String::from("some label")],
                 webhook_endpoint: String::from("https://chris-besch.com"),
+                webhook_cloudevents: None,
             }),
         ])
     )
@@ -471,15 +512,10 @@ async fn alert_grace_0_test() {
 async fn alert_grace_3_test() {
     let grace_period = 3;
     let cfg = Config {
-        runner_sets: vec![],
         github_timeout: Duration::from_millis(30),
         inbound_timeout: Duration::from_millis(30),
         grace_period,
-        allow_http: false,
-        created_template_path: String::from(""),
-        removed_template_path: String::from(""),
-        online_template_path: String::from(""),
-        offline_template_path: String::from(""),
+        ..test_cfg()
     };
 
     // initial setup
@@ -488,6 +524,7 @@ async fn alert_grace_3_test() {
         // the initial interpret_online needs to be set
         interpret_online: Some(true),
         online_for_github_api: true,
+        busy: false,
         online_state_change_since: 0,
         runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
         id: 2845,
@@ -495,6 +532,7 @@ async fn alert_grace_3_test() {
         os: String::from("todd-linux"),
         labels: vec![String::from("16x the detail")],
         webhook_endpoint: String::from("https://example.com"),
+        webhook_cloudevents: None,
     })]);
 
     // runner goes offline for GitHub for three heartbeats
@@ -505,6 +543,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("2"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -512,6 +551,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -523,6 +563,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("3"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -530,6 +571,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -541,6 +583,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("4"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -548,6 +591,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -562,6 +606,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("5"),
             interpret_online: None,
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -569,6 +614,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -583,6 +629,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("6"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -590,6 +637,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -601,6 +649,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("7"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -608,6 +657,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -619,6 +669,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("8"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -626,6 +677,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -637,6 +689,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("9"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -644,12 +697,14 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([RunnerStateChange::Offline(
             Runner {
                 utc_ping_time: String::from("8"),
                 interpret_online: Some(true),
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 3,
                 runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
                 id: 2845,
@@ -657,11 +712,13 @@ async fn alert_grace_3_test() {
                 os: String::from("todd-linux"),
                 labels: vec![String::from("16x the detail")],
                 webhook_endpoint: String::from("https://example.com"),
+                webhook_cloudevents: None,
             },
             Runner {
                 utc_ping_time: String::from("9"),
                 interpret_online: Some(false),
                 online_for_github_api: false,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
                 id: 2845,
@@ -669,6 +726,7 @@ async fn alert_grace_3_test() {
                 os: String::from("todd-linux"),
                 labels: vec![String::from("16x the detail")],
                 webhook_endpoint: String::from("https://example.com"),
+                webhook_cloudevents: None,
             },
         )]),
     )
@@ -682,6 +740,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("10"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -689,6 +748,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -702,6 +762,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("11"),
             interpret_online: None,
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -709,6 +770,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -720,6 +782,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("12"),
             interpret_online: None,
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -727,6 +790,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -738,6 +802,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("13"),
             interpret_online: None,
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -745,6 +810,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -758,6 +824,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("14"),
             interpret_online: None,
             online_for_github_api: false,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -765,6 +832,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -778,6 +846,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("15"),
             interpret_online: None,
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -785,6 +854,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -796,6 +866,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("16"),
             interpret_online: None,
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -803,6 +874,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -814,6 +886,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("17"),
             interpret_online: None,
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -821,6 +894,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([]),
     )
@@ -832,6 +906,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("18"),
             interpret_online: None,
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -839,12 +914,14 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })]),
         HashSet::from([RunnerStateChange::Online(
             Runner {
                 utc_ping_time: String::from("17"),
                 interpret_online: Some(false),
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 3,
                 runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
                 id: 2845,
@@ -852,11 +929,13 @@ async fn alert_grace_3_test() {
                 os: String::from("todd-linux"),
                 labels: vec![String::from("16x the detail")],
                 webhook_endpoint: String::from("https://example.com"),
+                webhook_cloudevents: None,
             },
             Runner {
                 utc_ping_time: String::from("18"),
                 interpret_online: Some(true),
                 online_for_github_api: true,
+                busy: false,
                 online_state_change_since: 0,
                 runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
                 id: 2845,
@@ -864,6 +943,7 @@ async fn alert_grace_3_test() {
                 os: String::from("todd-linux"),
                 labels: vec![String::from("16x the detail")],
                 webhook_endpoint: String::from("https://example.com"),
+                webhook_cloudevents: None,
             },
         )]),
     )
@@ -875,6 +955,7 @@ async fn alert_grace_3_test() {
             utc_ping_time: String::from("18"),
             interpret_online: Some(true),
             online_for_github_api: true,
+            busy: false,
             online_state_change_since: 0,
             runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
             id: 2845,
@@ -882,6 +963,7 @@ async fn alert_grace_3_test() {
             os: String::from("todd-linux"),
             labels: vec![String::from("16x the detail")],
             webhook_endpoint: String::from("https://example.com"),
+            webhook_cloudevents: None,
         })])
     );
 }
@@ -893,7 +975,7 @@ async fn perform_alert_test_step(
     expected_changes: HashSet<RunnerStateChange>,
 ) {
     let mut alert_handler = TestAlertHandler::new(expected_changes);
-    alert_all_changes_and_update_grace_period(&cfg, &runners, &mut new_runners, &mut alert_handler)
+    alert_all_changes_and_update_grace_period(cfg, runners, &mut new_runners, &mut alert_handler)
         .await
         .unwrap();
     alert_handler.assert_all_received();
@@ -0,0 +1,147 @@
+use axum::{extract::State, routing::post, Json, Router};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+use crate::alert::AlertHandler;
+use crate::alertmanager::{refresh_firing_alerts, AlertmanagerAlertHandler};
+use crate::notifier::close_firing_alerts;
+use crate::structs::{Config, Notifier, RunnerSetConfig, RunnerStateChange};
+use crate::test_fixtures::{test_cfg, test_runner, test_runner_set};
+
+type Received = Arc<Mutex<Vec<Value>>>;
+
+// stands in for the Alertmanager API
+async fn alertmanager_mock(address: &str) -> Received {
+    let received = Received::default();
+    let app = Router::new()
+        .route(
+            "/api/v2/alerts",
+            post(
+                |State(received): State<Received>, Json(alerts): Json<Value>| async move {
+                    received.lock().await.push(alerts);
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = TcpListener::bind(address).await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    received
+}
+
+fn get_test_runner_set(slug: &str, notifier: Notifier) -> RunnerSetConfig {
+    RunnerSetConfig {
+        webhook_endpoint: String::from("http://127.0.0.1:9015/api/v2/alerts"),
+        notifier,
+        ..test_runner_set(slug)
+    }
+}
+
+fn get_test_cfg() -> Config {
+    Config {
+        runner_sets: vec![
+            get_test_runner_set("my-org", Notifier::Alertmanager),
+            get_test_runner_set("other-org", Notifier::Webhook),
+        ],
+        allow_http: true,
+        ..test_cfg()
+    }
+}
+
+#[tokio::test]
+async fn alertmanager_test() {
+    let received = alertmanager_mock("127.0.0.1:9015").await;
+    let cfg = get_test_cfg();
+    let name = &cfg.runner_sets[0].name;
+    let (online, offline) = (test_runner(name, 42, true), test_runner(name, 42, false));
+    let mut alert_handler = AlertmanagerAlertHandler;
+    for change in [
+        // nothing to resolve yet
+        RunnerStateChange::Created(online.clone()),
+        RunnerStateChange::Offline(online.clone(), offline.clone()),
+        RunnerStateChange::Online(offline.clone(), online.clone()),
+    ] {
+        alert_handler.send_alert(&cfg, change).await.unwrap();
+    }
+    // only offline runners of the runner sets using alertmanager are sent again
+    let other_name = &cfg.runner_sets[1].name;
+    let mut second = offline.clone();
+    second.id = 43;
+    second.name = String::from("second-runner");
+    refresh_firing_alerts(
+        &cfg,
+        &[
+            online.clone(),
+            second,
+            offline.clone(),
+            test_runner(other_name, 42, false),
+        ],
+    )
+    .await
+    .unwrap();
+
+    let received = received.lock().await;
+    assert_eq!(received.len(), 3);
+    let firing = &received[0][0];
+    assert_eq!(
+        firing["labels"],
+        json!({
+            "alertname": "GitHubRunnerOffline",
+            "runner_key": format!("{}; runner id: 42", name),
+            "runner_set": "my-org",
+            "runner_set_kind": "org",
+            "runner": "runner-42",
+            "runner_id": "42",
+            "os": "linux",
+        })
+    );
+    assert_eq!(
+        firing["annotations"]["summary"],
+        "Runner runner-42 of my-org is offline"
+    );
+    assert_eq!(
+        firing["generatorURL"],
+        "https://github.com/organizations/my-org/settings/actions/runners"
+    );
+    assert!(firing.get("endsAt").is_none());
+    // the same identity resolves the alert
+    let resolved = &received[1][0];
+    assert_eq!(resolved["labels"], firing["labels"]);
+    assert!(resolved["endsAt"].is_string());
+
+    let refreshed = received[2].as_array().unwrap();
+    assert_eq!(refreshed.len(), 2);
+    assert_eq!(refreshed[0], *firing);
+    assert_eq!(refreshed[1]["labels"]["runner"], "second-runner");
+}
+
+#[tokio::test]
+async fn close_firing_alerts_test() {
+    let received = alertmanager_mock("127.0.0.1:9022").await;
+    let mut cfg = get_test_cfg();
+    for runner_set in cfg.runner_sets.iter_mut() {
+        runner_set.webhook_endpoint = String::from("http://127.0.0.1:9022/api/v2/alerts");
+    }
+    let name = &cfg.runner_sets[0].name;
+    let other_name = &cfg.runner_sets[1].name;
+    // only the offline runner of the alertmanager runner set has a firing alert
+    close_firing_alerts(
+        &cfg,
+        &[
+            test_runner(name, 42, true),
+            test_runner(name, 42, false),
+            test_runner(other_name, 42, false),
+        ],
+    )
+    .await
+    .unwrap();
+
+    let received = received.lock().await;
+    assert_eq!(received.len(), 1);
+    assert_eq!(
+        received[0][0]["labels"]["runner_key"],
+        format!("{}; runner id: 42", name)
+    );
+    assert!(received[0][0]["endsAt"].is_string());
+}
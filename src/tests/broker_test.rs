@@ -0,0 +1,345 @@
+use crate::broker::{event_topic, is_enabled, required_feature};
+use crate::config::parse_cfg;
+use crate::event::Event;
+use crate::event_sink::EventSinkConfig;
+use crate::structs::{Config, RunnerSetConfig, RunnerSetKind};
+use crate::test_fixtures::{
+    test_cfg, test_offline_change, test_runner, test_runner_set, write_temp_cfg,
+};
+
+fn get_test_cfg(event_sinks: Vec<EventSinkConfig>) -> Config {
+    let repo = RunnerSetConfig {
+        name: String::from("repo: my-org/my.repo; github: https://api.github.com"),
+        kind: RunnerSetKind::Repo,
+        ..test_runner_set("my-org/my.repo")
+    };
+    let special = RunnerSetConfig {
+        event_topic: Some(String::from("special.{kind}")),
+        ..test_runner_set("special-org")
+    };
+    Config {
+        runner_sets: vec![repo, special],
+        allow_http: true,
+        event_sinks,
+        ..test_cfg()
+    }
+}
+
+#[test]
+fn event_topic_test() {
+    let cfg = get_test_cfg(vec![]);
+    let topic = "gh_runner_uptime.{runner_set}.{kind}";
+    // only letters, digits, - and _ are kept
+    assert_eq!(
+        event_topic(
+            &cfg,
+            topic,
+            &Event::new(
+                &cfg,
+                &test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false))
+            )
+        ),
+        "gh_runner_uptime.my-org_my_repo.offline"
+    );
+    // the runner set overrides the sink
+    assert_eq!(
+        event_topic(
+            &cfg,
+            topic,
+            &Event::new(
+                &cfg,
+                &test_offline_change(test_runner(&cfg.runner_sets[1].name, 42, false))
+            )
+        ),
+        "special.offline"
+    );
+    assert_eq!(
+        event_topic(
+            &cfg,
+            topic,
+            &Event::new(
+                &cfg,
+                &test_offline_change(test_runner("org: gone; github: x", 42, false))
+            )
+        ),
+        "gh_runner_uptime.unknown.offline"
+    );
+}
+
+#[test]
+fn broker_cfg_test() {
+    let cfg_path = write_temp_cfg(
+        "broker_cfg",
+        "grace_period: 0
+event_sinks:
+  - type: nats
+    url: nats://nats:4222
+    subject: runners.{runner_set}.{kind}
+orgs:
+  - name: someOrg
+    github_base_uri: https://api.github.com
+    github_pat: some_pat
+    webhook_endpoint: https://example.com
+    format: generic-json
+    event_topic: some-org.{kind}
+",
+    );
+    let result = parse_cfg(&cfg_path, false);
+    std::fs::remove_file(&cfg_path).unwrap();
+    let sink = EventSinkConfig::Nats {
+        url: String::from("nats://nats:4222"),
+        subject: String::from("runners.{runner_set}.{kind}"),
+    };
+    assert_eq!(required_feature(&sink), Some("nats"));
+    if is_enabled("nats") {
+        let cfg = result.unwrap();
+        assert_eq!(cfg.event_sinks, vec![sink]);
+        assert_eq!(
+            cfg.runner_sets[0].event_topic.as_deref(),
+            Some("some-org.{kind}")
+        );
+    } else {
+        let e = result.unwrap_err();
+        assert!(
+            format!("{:#}", e).contains(
+                "the nats event sink needs gh_runner_uptime to be built with the nats feature"
+            ),
+            "{:#}",
+            e
+        );
+    }
+}
+
+// The stand-ins speak just enough of each protocol to receive a message.
+#[cfg(feature = "nats")]
+#[tokio::test]
+async fn nats_test() {
+    use crate::broker::publish;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:9017").await.unwrap();
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        writer
+            .write_all(b"INFO {\"server_id\":\"test\",\"version\":\"2.10.0\",\"max_payload\":1048576,\"proto\":1,\"headers\":true}\r\n")
+            .await
+            .unwrap();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+            if line.starts_with("PING") {
+                writer.write_all(b"PONG\r\n").await.unwrap();
+            } else if let Some(args) = line.strip_prefix("PUB ") {
+                let args: Vec<&str> = args.split_whitespace().collect();
+                let mut payload = vec![0; args[1].parse::<usize>().unwrap() + 2];
+                reader.read_exact(&mut payload).await.unwrap();
+                payload.truncate(payload.len() - 2);
+                return (args[0].to_string(), String::from_utf8(payload).unwrap());
+            }
+        }
+    });
+
+    let sink = EventSinkConfig::Nats {
+        url: String::from("nats://127.0.0.1:9017"),
+        subject: String::from("runners.{runner_set}.{kind}"),
+    };
+    let cfg = get_test_cfg(vec![sink.clone()]);
+    let event = Event::new(
+        &cfg,
+        &test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false)),
+    );
+    let line = serde_json::to_string(&event).unwrap();
+    publish(&cfg, &sink, &event, &line).await.unwrap();
+    let (subject, payload) = server.await.unwrap();
+    assert_eq!(subject, "runners.my-org_my_repo.offline");
+    assert_eq!(payload, line);
+}
+
+#[cfg(feature = "redis")]
+#[tokio::test]
+async fn redis_test() {
+    use crate::broker::publish;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:9018").await.unwrap();
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        loop {
+            // every command is an array of bulk strings
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            let count: usize = line.trim_start_matches('*').trim().parse().unwrap();
+            let mut command = vec![];
+            for _ in 0..count {
+                line.clear();
+                reader.read_line(&mut line).await.unwrap();
+                let len: usize = line.trim_start_matches('$').trim().parse().unwrap();
+                let mut arg = vec![0; len + 2];
+                reader.read_exact(&mut arg).await.unwrap();
+                arg.truncate(len);
+                command.push(String::from_utf8(arg).unwrap());
+            }
+            if command[0] == "XADD" {
+                writer.write_all(b"$3\r\n1-0\r\n").await.unwrap();
+                return command;
+            }
+            writer.write_all(b"+OK\r\n").await.unwrap();
+        }
+    });
+
+    let sink = EventSinkConfig::Redis {
+        url: String::from("redis://127.0.0.1:9018"),
+        stream: String::from("runners"),
+    };
+    let cfg = get_test_cfg(vec![sink.clone()]);
+    let event = Event::new(
+        &cfg,
+        &test_offline_change(test_runner(&cfg.runner_sets[1].name, 42, false)),
+    );
+    let line = serde_json::to_string(&event).unwrap();
+    publish(&cfg, &sink, &event, &line).await.unwrap();
+    assert_eq!(
+        server.await.unwrap(),
+        vec!["XADD", "special.offline", "*", "event", &line]
+    );
+}
+
+#[cfg(feature = "mqtt")]
+#[tokio::test]
+async fn mqtt_test() {
+    use crate::broker::publish;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    // the packet type and its body
+    async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let packet_type = stream.read_u8().await.unwrap();
+        let (mut len, mut shift) = (0, 0);
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            len |= ((byte & 0x7F) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body).await.unwrap();
+        (packet_type, body)
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:9019").await.unwrap();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        loop {
+            let (packet_type, body) = read_packet(&mut stream).await;
+            match packet_type >> 4 {
+                // CONNECT -> CONNACK
+                1 => stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap(),
+                // PUBLISH with QoS 1 -> PUBACK
+                3 => {
+                    let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                    let topic = String::from_utf8(body[2..2 + topic_len].to_vec()).unwrap();
+                    let packet_id = &body[2 + topic_len..4 + topic_len];
+                    let payload = String::from_utf8(body[4 + topic_len..].to_vec()).unwrap();
+                    stream
+                        .write_all(&[0x40, 0x02, packet_id[0], packet_id[1]])
+                        .await
+                        .unwrap();
+                    // a broker keeps the connection open
+                    return (packet_type & 0x06, topic, payload, stream);
+                }
+                // PINGREQ -> PINGRESP
+                12 => stream.write_all(&[0xD0, 0x00]).await.unwrap(),
+                _ => {}
+            }
+        }
+    });
+
+    let sink = EventSinkConfig::Mqtt {
+        url: String::from("mqtt://127.0.0.1:9019?client_id=gh_runner_uptime"),
+        topic: String::from("runners/{runner_set}/{kind}"),
+    };
+    let cfg = get_test_cfg(vec![sink.clone()]);
+    let event = Event::new(
+        &cfg,
+        &test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false)),
+    );
+    let line = serde_json::to_string(&event).unwrap();
+    publish(&cfg, &sink, &event, &line).await.unwrap();
+    let (qos, topic, payload, _stream) =
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+    // at least once
+    assert_eq!(qos, 0x02);
+    assert_eq!(topic, "runners/my-org_my_repo/offline");
+    assert_eq!(payload, line);
+}
+
+// a broker that accepts the connection but never answers must not block the scan
+#[cfg(feature = "mqtt")]
+#[tokio::test]
+async fn mqtt_timeout_test() {
+    use crate::broker::publish;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:9021").await.unwrap();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        // until the connection is dropped
+        let mut buf = vec![0; 1024];
+        while stream.read(&mut buf).await.unwrap() > 0 {}
+    });
+
+    let sink = EventSinkConfig::Mqtt {
+        url: String::from("mqtt://127.0.0.1:9021?client_id=gh_runner_uptime"),
+        topic: String::from("runners"),
+    };
+    let mut cfg = get_test_cfg(vec![sink.clone()]);
+    cfg.inbound_timeout = std::time::Duration::from_millis(300);
+    let event = Event::new(
+        &cfg,
+        &test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false)),
+    );
+    let started = std::time::Instant::now();
+    let e = publish(&cfg, &sink, &event, "{}").await.unwrap_err();
+    assert!(format!("{:#}", e).contains("timed out"), "{:#}", e);
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    // the unconfirmed connection is closed
+    tokio::time::timeout(std::time::Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+// needs a broker, e.g. `docker run -p 9092:9092 apache/kafka`:
+// cargo test --features kafka -- --ignored kafka_test
+#[cfg(feature = "kafka")]
+#[tokio::test]
+#[ignore]
+async fn kafka_test() {
+    use crate::broker::publish;
+
+    let sink = EventSinkConfig::Kafka {
+        brokers: String::from("127.0.0.1:9092"),
+        topic: String::from("gh_runner_uptime.{runner_set}"),
+    };
+    let mut cfg = get_test_cfg(vec![sink.clone()]);
+    cfg.inbound_timeout = std::time::Duration::from_secs(10);
+    let event = Event::new(
+        &cfg,
+        &test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false)),
+    );
+    let line = serde_json::to_string(&event).unwrap();
+    publish(&cfg, &sink, &event, &line).await.unwrap();
+}
@@ -0,0 +1,82 @@
+use crate::alert::AlertHandler;
+use crate::check::validate_cfg;
+use crate::inbound_alert_handler::InboundAlertHandler;
+use crate::structs::{Config, Runner, RunnerSetConfig, RunnerStateChange};
+use crate::test_fixtures::{test_cfg_with_templates, test_runner_set};
+
+fn get_test_runner_set(name: &str, webhook_endpoint: &str) -> RunnerSetConfig {
+    RunnerSetConfig {
+        name: String::from(name),
+        webhook_endpoint: String::from(webhook_endpoint),
+        ..test_runner_set("test-org")
+    }
+}
+
+fn get_test_cfg(runner_sets: Vec<RunnerSetConfig>) -> Config {
+    Config {
+        runner_sets,
+        report_template_path: Some(String::from("src/tests/test_report_template.txt.j2")),
+        ..test_cfg_with_templates()
+    }
+}
+
+#[test]
+fn validate_cfg_test() {
+    let cfg = get_test_cfg(vec![get_test_runner_set(
+        "org: a",
+        "https://example.com?token=secret",
+    )]);
+    assert_eq!(validate_cfg(&cfg), Vec::<String>::new());
+
+    let cfg = get_test_cfg(vec![
+        get_test_runner_set("org: a", "https://example.com?token=secret"),
+        get_test_runner_set("org: a", "https://example.com?token=secret"),
+        get_test_runner_set("org: b", "http://example.com?token=secret"),
+        get_test_runner_set("org: c", "example.com?token=secret"),
+    ]);
+    let problems = validate_cfg(&cfg);
+    assert_eq!(problems.len(), 3);
+    assert_eq!(problems[0], "org: a is configured more than once");
+    assert_eq!(problems[1], "org: b: webhook_endpoint must use https");
+    assert!(problems[2].starts_with("org: c: webhook_endpoint is not a valid URL"));
+    // the webhook endpoints contain secrets
+    assert!(problems.iter().all(|p| !p.contains("secret")));
+
+    // templates that compile but fail to render are found as well
+    let mut cfg = get_test_cfg(vec![get_test_runner_set(
+        "org: a",
+        "https://example.com?token=secret",
+    )]);
+    cfg.offline_template_path = Some(String::from("src/tests/test_broken_template.txt.j2"));
+    let problems = validate_cfg(&cfg);
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].starts_with("Failed to render the offline template"));
+
+    cfg.offline_template_path = Some(String::from("src/tests/missing_template.txt.j2"));
+    let problems = validate_cfg(&cfg);
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].starts_with("Failed to compile the templates"));
+}
+
+#[tokio::test]
+async fn dry_run_test() {
+    let mut cfg = get_test_cfg(vec![]);
+    cfg.dry_run = true;
+    let mut alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    // nothing listens on this port
+    let change = RunnerStateChange::Created(Runner {
+        utc_ping_time: String::from("1"),
+        interpret_online: Some(true),
+        online_for_github_api: true,
+        busy: false,
+        online_state_change_since: 0,
+        runner_set: String::from("org: a"),
+        id: 1,
+        name: String::from("runner-1"),
+        os: String::from("linux"),
+        labels: vec![],
+        webhook_endpoint: String::from("https://127.0.0.1:1/webhook"),
+        webhook_cloudevents: None,
+    });
+    alert_handler.send_alert(&cfg, change).await.unwrap();
+}
@@ -0,0 +1,76 @@
+use crate::cli::{format_runner_table, load_state, save_state};
+use crate::github::key_runner;
+use crate::structs::{Config, Runner, RunnerMap, RunnerSetConfig};
+use crate::test_fixtures::{temp_path, test_cfg_with_templates, test_runner, test_runner_set};
+
+fn get_test_cfg() -> Config {
+    Config {
+        runner_sets: vec![RunnerSetConfig {
+            name: String::from("org: a"),
+            webhook_endpoint: String::from("https://new.example.com?token=secret"),
+            ..test_runner_set("test-org")
+        }],
+        ..test_cfg_with_templates()
+    }
+}
+
+#[test]
+fn state_file_test() {
+    let path = &temp_path("state");
+    let _ = std::fs::remove_file(path);
+    let cfg = get_test_cfg();
+
+    // no state file yet
+    assert_eq!(load_state(path, &cfg).unwrap(), None);
+
+    let runners: RunnerMap = [
+        key_runner(Runner {
+            labels: vec![String::from("gpu")],
+            ..test_runner("org: a", 1, true)
+        }),
+        key_runner(Runner {
+            labels: vec![String::from("gpu")],
+            ..test_runner("org: removed", 2, true)
+        }),
+    ]
+    .into_iter()
+    .collect();
+    save_state(path, &runners).unwrap();
+    let json = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    // the webhook endpoints contain secrets
+    assert!(!json.contains("secret"));
+    std::fs::write(path, json).unwrap();
+
+    let loaded = load_state(path, &cfg).unwrap().unwrap();
+    std::fs::remove_file(path).unwrap();
+    // runner sets that aren't configured anymore are dropped
+    assert_eq!(loaded.len(), 1);
+    let runner = loaded.values().next().unwrap();
+    assert_eq!(runner.id, 1);
+    assert_eq!(
+        runner.webhook_endpoint,
+        "https://new.example.com?token=secret"
+    );
+}
+
+#[test]
+fn format_runner_table_test() {
+    let mut offline = Runner {
+        labels: vec![],
+        ..test_runner("org: a", 12, true)
+    };
+    offline.online_for_github_api = false;
+    offline.busy = true;
+    let online = Runner {
+        labels: vec![String::from("gpu"), String::from("x64")],
+        ..test_runner("org: a", 1, true)
+    };
+    assert_eq!(
+        format_runner_table(&[&online, &offline]),
+        "RUNNER SET  ID  NAME       STATUS   BUSY  OS     LABELS
+org: a      1   runner-1   online   no    linux  gpu,x64
+org: a      12  runner-12  offline  yes   linux
+"
+    );
+}
@@ -0,0 +1,189 @@
+use axum::{body::Bytes, extract::State, http::HeaderMap, routing::post, Router};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+use crate::alert::AlertHandler;
+use crate::cloudevents::{CloudEventsMode, STRUCTURED_CONTENT_TYPE};
+use crate::config::parse_cfg;
+use crate::event::Event;
+use crate::inbound_alert_handler::InboundAlertHandler;
+use crate::structs::{Config, Runner, RunnerSetConfig, RunnerStateChange, WebhookOptions};
+use crate::template::TemplateFormat;
+use crate::test_fixtures::{
+    test_cfg, test_offline_change, test_runner, test_runner_set, write_temp_cfg,
+};
+
+fn get_test_cfg(cloudevents: CloudEventsMode, webhook_endpoint: &str) -> Config {
+    Config {
+        runner_sets: vec![RunnerSetConfig {
+            webhook_endpoint: String::from(webhook_endpoint),
+            webhook: WebhookOptions {
+                // as parsed with `format: generic-json`
+                content_type: Some(String::from(TemplateFormat::GenericJson.content_type())),
+                cloudevents: Some(cloudevents),
+                ..WebhookOptions::default()
+            },
+            ..test_runner_set("my-org")
+        }],
+        allow_http: true,
+        format: Some(TemplateFormat::GenericJson),
+        ..test_cfg()
+    }
+}
+
+#[test]
+fn cloudevents_cfg_test() {
+    let write_cfg = |notifier: &str| {
+        write_temp_cfg(
+            "cloudevents_cfg",
+            &format!(
+                "grace_period: 0
+format: generic-json
+orgs:
+  - name: someOrg
+    github_base_uri: https://api.github.com
+    github_pat: some_pat
+    webhook_endpoint: https://example.com
+    notifier: {}
+    webhook_cloudevents: binary
+",
+                notifier
+            ),
+        )
+    };
+    let cfg_path = write_cfg("webhook");
+    let cfg = parse_cfg(&cfg_path, false).unwrap();
+    assert_eq!(
+        cfg.runner_sets[0].webhook.cloudevents,
+        Some(CloudEventsMode::Binary)
+    );
+    // the other notifiers have their own message formats
+    let cfg_path = write_cfg("discord");
+    let e = parse_cfg(&cfg_path, false).unwrap_err();
+    assert!(
+        format!("{:#}", e).contains("webhook_cloudevents needs the webhook notifier"),
+        "{:#}",
+        e
+    );
+    std::fs::remove_file(&cfg_path).unwrap();
+}
+
+#[test]
+fn cloudevents_structured_test() {
+    let cfg = get_test_cfg(CloudEventsMode::Structured, "https://example.com/webhook");
+    let change = test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false));
+    let alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    let request = alert_handler.render_alert(&cfg, &change).unwrap();
+    assert_eq!(
+        request.webhook.content_type.as_deref(),
+        Some(STRUCTURED_CONTENT_TYPE)
+    );
+    let body: Value = serde_json::from_str(&request.body).unwrap();
+    let event = Event::new(&cfg, &change);
+    assert_eq!(body["specversion"], "1.0");
+    assert_eq!(body["id"], event.id);
+    assert_eq!(body["type"], "com.github.runner.offline");
+    assert_eq!(
+        body["source"],
+        "https://github.com/organizations/my-org/settings/actions/runners"
+    );
+    assert_eq!(body["subject"], "runner-42");
+    assert_eq!(body["datacontenttype"], "application/json");
+    // the rendered alert is the data
+    assert_eq!(body["data"]["title"], "Runner runner-42 went offline");
+    assert_eq!(body["data"]["event"]["id"], event.id);
+}
+
+#[test]
+fn cloudevents_removed_runner_set_test() {
+    let cfg = get_test_cfg(CloudEventsMode::Structured, "https://example.com/webhook");
+    // the runner set isn't configured anymore, the runner remembers the mode
+    let mut runner = test_runner("org: gone; github: https://api.github.com", 42, false);
+    runner.webhook_endpoint = String::from("https://example.com/webhook");
+    runner.webhook_cloudevents = Some(CloudEventsMode::Structured);
+    let change = RunnerStateChange::Removed(runner);
+    let alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    let request = alert_handler.render_alert(&cfg, &change).unwrap();
+    assert_eq!(
+        request.webhook.content_type.as_deref(),
+        Some(STRUCTURED_CONTENT_TYPE)
+    );
+    let body: Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(body["id"], Event::new(&cfg, &change).id);
+    assert_eq!(body["type"], "com.github.runner.removed");
+    assert!(body["source"].as_str().unwrap().starts_with("urn:uuid:"));
+    // without the content type of the runner set the alert is embedded as string
+    assert!(body["data"].as_str().unwrap().contains("was removed"));
+}
+
+#[test]
+fn cloudevents_invalid_json_test() {
+    let mut cfg = get_test_cfg(CloudEventsMode::Structured, "https://example.com/webhook");
+    // plain text alerts claiming to be JSON
+    cfg.runner_sets[0].format = Some(TemplateFormat::PlainText);
+    let change = test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false));
+    let alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    let e = alert_handler.render_alert(&cfg, &change).unwrap_err();
+    assert!(
+        format!("{:#}", e).contains("JSON content type, but isn't valid JSON"),
+        "{:#}",
+        e
+    );
+}
+
+#[derive(Debug)]
+struct ReceivedRequest {
+    headers: HeaderMap,
+    body: String,
+}
+
+#[tokio::test]
+async fn cloudevents_binary_test() {
+    let received = Arc::new(Mutex::new(vec![]));
+    let app = Router::new()
+        .route(
+            "/webhook",
+            post(
+                |State(received): State<Arc<Mutex<Vec<ReceivedRequest>>>>,
+                 headers: HeaderMap,
+                 body: Bytes| async move {
+                    received.lock().await.push(ReceivedRequest {
+                        headers,
+                        body: String::from_utf8(body.to_vec()).unwrap(),
+                    });
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = TcpListener::bind("127.0.0.1:9020").await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let cfg = get_test_cfg(CloudEventsMode::Binary, "http://127.0.0.1:9020/webhook");
+    // the alert is sent to the endpoint the runner was scanned with
+    let change = test_offline_change(Runner {
+        webhook_endpoint: cfg.runner_sets[0].webhook_endpoint.clone(),
+        ..test_runner(&cfg.runner_sets[0].name, 42, false)
+    });
+    let event = Event::new(&cfg, &change);
+    let mut alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    alert_handler.send_alert(&cfg, change).await.unwrap();
+
+    let received = received.lock().await;
+    assert_eq!(received.len(), 1);
+    let headers = &received[0].headers;
+    assert_eq!(headers["ce-specversion"], "1.0");
+    assert_eq!(headers["ce-id"], event.id.as_str());
+    assert_eq!(headers["ce-type"], "com.github.runner.offline");
+    assert_eq!(
+        headers["ce-source"],
+        "https://github.com/organizations/my-org/settings/actions/runners"
+    );
+    assert_eq!(headers["ce-subject"], "runner-42");
+    assert!(headers.contains_key("ce-time"));
+    // the content type is the datacontenttype, the body is the data
+    assert_eq!(headers["content-type"], "application/json");
+    let body: Value = serde_json::from_str(&received[0].body).unwrap();
+    assert_eq!(body["title"], "Runner runner-42 went offline");
+}
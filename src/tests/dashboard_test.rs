@@ -0,0 +1,69 @@
+use chrono::Duration;
+
+use crate::dashboard::{format_duration, render_dashboard};
+use crate::github::key_runner;
+use crate::structs::{DeliveredAlert, MonitorState, Runner, RunnerStateChange};
+use crate::test_fixtures::test_runner;
+
+#[test]
+fn format_duration_test() {
+    assert_eq!(format_duration(Duration::seconds(42)), "42s");
+    assert_eq!(format_duration(Duration::seconds(125)), "2m 5s");
+    assert_eq!(format_duration(Duration::minutes(185)), "3h 5m");
+    assert_eq!(format_duration(Duration::hours(76)), "3d 4h");
+    assert_eq!(format_duration(Duration::seconds(-5)), "0s");
+}
+
+#[test]
+fn render_dashboard_test() {
+    let sets = vec![String::from("org: a"), String::from("org: b")];
+    let mut state = MonitorState::default();
+    for runner in [
+        Runner {
+            busy: true,
+            labels: vec![String::from("gpu")],
+            ..test_runner("org: a", 1, true)
+        },
+        Runner {
+            busy: false,
+            labels: vec![String::from("<script>")],
+            ..test_runner("org: a", 2, false)
+        },
+        Runner {
+            busy: true,
+            labels: vec![String::from("gpu")],
+            ..test_runner("org: b", 3, true)
+        },
+    ] {
+        let (key, runner) = key_runner(runner);
+        state.state_since.insert(key.clone(), chrono::Utc::now());
+        state.runners.insert(key, runner);
+    }
+    state.record_alerts(&[DeliveredAlert::new(&RunnerStateChange::Created(Runner {
+        busy: true,
+        labels: vec![String::from("gpu")],
+        ..test_runner("org: b", 3, true)
+    }))]);
+
+    let html = render_dashboard(&sets, &state, None, None).unwrap();
+    assert!(html.contains("runner-1"));
+    assert!(html.contains("runner-2"));
+    assert!(html.contains("runner-3"));
+    assert!(html.contains("created"));
+    // labels are escaped
+    assert!(html.contains("&lt;script&gt;"));
+    assert!(!html.contains("<script>"));
+    // no external resources
+    assert!(!html.contains("http://") && !html.contains("https://"));
+
+    let html = render_dashboard(&sets, &state, None, Some("gpu")).unwrap();
+    assert!(html.contains("runner-1"));
+    assert!(!html.contains("runner-2"));
+    assert!(html.contains("runner-3"));
+
+    let html = render_dashboard(&sets, &state, Some("org: a"), None).unwrap();
+    assert!(html.contains("runner-1"));
+    assert!(html.contains("runner-2"));
+    assert!(!html.contains("runner-3"));
+    assert!(html.contains("no alerts since startup"));
+}
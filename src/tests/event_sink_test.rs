@@ -0,0 +1,324 @@
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+use tokio::net::{UnixDatagram, UnixListener};
+
+use crate::alert::AlertHandler;
+use crate::config::parse_cfg;
+use crate::event::{Event, EventKind, EVENT_SCHEMA_VERSION};
+use crate::event_sink::{append_line, syslog_message, EventSinkAlertHandler, EventSinkConfig};
+use crate::structs::{Config, Runner, RunnerStateChange};
+use crate::test_alert_handler::TestAlertHandler;
+use crate::test_fixtures::{
+    temp_dir, test_cfg, test_offline_change, test_runner, test_runner_set, write_temp_cfg,
+};
+
+struct FailingAlertHandler;
+
+impl AlertHandler for FailingAlertHandler {
+    async fn deliver_alert(
+        &mut self,
+        _cfg: &Config,
+        _change: RunnerStateChange,
+        _event: &Event,
+    ) -> Result<()> {
+        bail!("webhook is down")
+    }
+}
+
+// fails the alerts of one runner until it has failed `failures` times
+struct FlakyAlertHandler {
+    runner_id: i64,
+    failures: usize,
+}
+
+impl AlertHandler for FlakyAlertHandler {
+    async fn deliver_alert(
+        &mut self,
+        _cfg: &Config,
+        change: RunnerStateChange,
+        _event: &Event,
+    ) -> Result<()> {
+        if change.runner().id == self.runner_id && self.failures > 0 {
+            self.failures -= 1;
+            bail!("webhook is down");
+        }
+        Ok(())
+    }
+}
+
+fn get_test_cfg(event_sinks: Vec<EventSinkConfig>) -> Config {
+    Config {
+        runner_sets: vec![test_runner_set("my-org")],
+        allow_http: true,
+        event_sinks,
+        ..test_cfg()
+    }
+}
+
+fn read_events(path: &Path) -> Vec<Value> {
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn event_test() {
+    let cfg = get_test_cfg(vec![]);
+    let name = &cfg.runner_sets[0].name;
+    let (online, offline) = (test_runner(name, 42, true), test_runner(name, 42, false));
+    let change = RunnerStateChange::Offline(online.clone(), offline.clone());
+    let event = Event::new(&cfg, &change);
+    assert_eq!(event.schema_version, EVENT_SCHEMA_VERSION);
+    assert_eq!(event.kind, EventKind::Offline);
+    assert_eq!(event.runner_set.slug.as_deref(), Some("my-org"));
+    assert_eq!(event.runner_set.kind.as_deref(), Some("org"));
+    assert_eq!(event.runner.online, Some(false));
+    assert_eq!(event.previous_runner.as_ref().unwrap().online, Some(true));
+
+    // the schema is independent of the Runner struct
+    let value = serde_json::to_value(&event).unwrap();
+    let mut keys: Vec<&str> = value["runner"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(|k| k.as_str())
+        .collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            "busy",
+            "id",
+            "labels",
+            "name",
+            "online",
+            "os",
+            "utc_ping_time"
+        ]
+    );
+
+    // runners of removed runner sets are still written
+    let mut removed = offline.clone();
+    removed.runner_set = String::from("org: gone; github: https://api.github.com");
+    let event = Event::new(&cfg, &RunnerStateChange::Removed(removed));
+    assert_eq!(event.runner_set.slug, None);
+    assert_eq!(event.previous_runner, None);
+}
+
+#[tokio::test]
+async fn event_sink_test() {
+    let dir = temp_dir("event_sinks");
+    let file_path = dir.join("events.jsonl");
+    let socket_path = dir.join("events.sock");
+    let syslog_path = dir.join("log");
+    let listener = UnixListener::bind(&socket_path).unwrap();
+    let syslog = UnixDatagram::bind(&syslog_path).unwrap();
+    let cfg = get_test_cfg(vec![
+        EventSinkConfig::File {
+            path: file_path.to_str().unwrap().to_string(),
+            max_bytes: 1024 * 1024,
+            max_files: 1,
+        },
+        EventSinkConfig::UnixSocket {
+            path: socket_path.to_str().unwrap().to_string(),
+        },
+        EventSinkConfig::Syslog {
+            path: syslog_path.to_str().unwrap().to_string(),
+        },
+    ]);
+    let name = &cfg.runner_sets[0].name;
+    let (online, offline) = (test_runner(name, 42, true), test_runner(name, 42, false));
+    let change = RunnerStateChange::Offline(online, offline);
+
+    let socket_reader = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut line = String::new();
+        stream.read_to_string(&mut line).await.unwrap();
+        line
+    });
+    let mut alert_handler =
+        EventSinkAlertHandler::new(TestAlertHandler::new(HashSet::from([change.clone()])));
+    alert_handler
+        .send_alert(&cfg, change.clone())
+        .await
+        .unwrap();
+    alert_handler.inner.assert_all_received();
+
+    let events = read_events(&file_path);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["kind"], "offline");
+    assert_eq!(events[0]["schema_version"], 1);
+    assert_eq!(events[0]["runner"]["name"], "runner-42");
+    // the handler got the same event, down to its time
+    assert_eq!(
+        serde_json::to_value(&alert_handler.inner.events[0]).unwrap(),
+        events[0]
+    );
+
+    let line = socket_reader.await.unwrap();
+    assert!(line.ends_with('\n'));
+    assert_eq!(serde_json::from_str::<Value>(&line).unwrap(), events[0]);
+
+    let mut buf = [0; 4096];
+    let len = syslog.recv(&mut buf).await.unwrap();
+    let message = String::from_utf8_lossy(&buf[..len]);
+    // daemon.warning
+    assert!(message.starts_with("<28>"), "{}", message);
+    let json = message.split_once("]: ").unwrap().1;
+    assert_eq!(serde_json::from_str::<Value>(json).unwrap(), events[0]);
+
+    // undelivered alerts are retried with the next scan, so they aren't written yet
+    let mut alert_handler = EventSinkAlertHandler::new(FailingAlertHandler);
+    assert!(alert_handler
+        .send_alert(&cfg, change.clone())
+        .await
+        .is_err());
+    assert_eq!(read_events(&file_path).len(), 1);
+
+    // a broken sink doesn't fail the alert
+    let cfg = get_test_cfg(vec![EventSinkConfig::UnixSocket {
+        path: dir.join("missing.sock").to_str().unwrap().to_string(),
+    }]);
+    let mut alert_handler =
+        EventSinkAlertHandler::new(TestAlertHandler::new(HashSet::from([change.clone()])));
+    alert_handler.send_alert(&cfg, change).await.unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn event_file_rotation_test() {
+    let dir = temp_dir("event_rotation");
+    let path = dir.join("events.jsonl");
+    let path_str = path.to_str().unwrap();
+    for i in 0..5 {
+        // 9 bytes per line, two fit into 20 bytes
+        append_line(path_str, 20, 2, &format!("{{\"i\":{}}}", i)).unwrap();
+    }
+    let read = |suffix: &str| std::fs::read_to_string(format!("{}{}", path_str, suffix)).unwrap();
+    assert_eq!(read(""), "{\"i\":4}\n");
+    assert_eq!(read(".1"), "{\"i\":2}\n{\"i\":3}\n");
+    assert_eq!(read(".2"), "{\"i\":0}\n{\"i\":1}\n");
+    assert!(!Path::new(&format!("{}.3", path_str)).exists());
+
+    // a line larger than max_bytes still gets its own file
+    append_line(path_str, 4, 0, "{\"i\":5}").unwrap();
+    assert_eq!(read(""), "{\"i\":5}\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(syslog_message(EventKind::Online, "{}").starts_with("<30>"));
+}
+
+#[test]
+fn event_sink_cfg_test() {
+    let runner_set = "grace_period: 0
+orgs:
+  - name: someOrg
+    github_base_uri: https://api.github.com
+    github_pat: some_pat
+    webhook_endpoint: https://example.com
+    format: generic-json
+";
+    let cfg_path = write_temp_cfg(
+        "event_sink_cfg",
+        &format!(
+            "{}event_sinks:
+  - type: stdout
+  - type: file
+    path: /var/log/events.jsonl
+  - type: unix_socket
+    path: /run/vector.sock
+  - type: syslog
+",
+            runner_set
+        ),
+    );
+    let cfg = parse_cfg(&cfg_path, false).unwrap();
+    assert_eq!(
+        cfg.event_sinks,
+        vec![
+            EventSinkConfig::Stdout,
+            EventSinkConfig::File {
+                path: String::from("/var/log/events.jsonl"),
+                max_bytes: 10 * 1024 * 1024,
+                max_files: 5,
+            },
+            EventSinkConfig::UnixSocket {
+                path: String::from("/run/vector.sock"),
+            },
+            EventSinkConfig::Syslog {
+                path: String::from("/dev/log"),
+            },
+        ]
+    );
+
+    std::fs::write(
+        &cfg_path,
+        format!(
+            "{}event_sinks:
+  - type: file
+    path: /var/log/events.jsonl
+    max_bytes: 0
+",
+            runner_set
+        ),
+    )
+    .unwrap();
+    let e = parse_cfg(&cfg_path, false).unwrap_err();
+    assert!(
+        format!("{:#}", e).contains("max_bytes of the event file /var/log/events.jsonl"),
+        "{:#}",
+        e
+    );
+    std::fs::remove_file(&cfg_path).unwrap();
+}
+
+#[tokio::test]
+async fn repeated_event_test() {
+    let dir = temp_dir("repeated_events");
+    let file_path = dir.join("events.jsonl");
+    let cfg = get_test_cfg(vec![EventSinkConfig::File {
+        path: file_path.to_str().unwrap().to_string(),
+        max_bytes: 1024 * 1024,
+        max_files: 1,
+    }]);
+    let name = &cfg.runner_sets[0].name;
+    let mut alert_handler = EventSinkAlertHandler::new(FlakyAlertHandler {
+        runner_id: 43,
+        failures: 1,
+    });
+    // the first alert of the scan is delivered, the second one fails
+    alert_handler
+        .send_alert(&cfg, test_offline_change(test_runner(name, 42, false)))
+        .await
+        .unwrap();
+    assert!(alert_handler
+        .send_alert(&cfg, test_offline_change(test_runner(name, 43, false)))
+        .await
+        .is_err());
+    assert_eq!(read_events(&file_path).len(), 1);
+
+    // the state isn't saved, so the next scan finds and delivers both changes again
+    let later = |id| Runner {
+        utc_ping_time: String::from("2024-05-01T00:01:00+00:00"),
+        ..test_runner(name, id, false)
+    };
+    for id in [42, 43] {
+        alert_handler
+            .send_alert(&cfg, test_offline_change(later(id)))
+            .await
+            .unwrap();
+    }
+    // sinks are at least once: the event of the first runner is written again, with a new id
+    let events = read_events(&file_path);
+    let runner_ids: Vec<&Value> = events.iter().map(|e| &e["runner"]["id"]).collect();
+    assert_eq!(runner_ids, [42, 42, 43]);
+    assert_ne!(events[0]["id"], events[1]["id"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
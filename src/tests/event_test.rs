@@ -0,0 +1,108 @@
+use serde_json::Value;
+
+use crate::event::{event_schema, Event, EventKind};
+use crate::inbound_alert_handler::InboundAlertHandler;
+use crate::structs::{Config, RunnerSetConfig, RunnerStateChange, WebhookOptions};
+use crate::template::TemplateFormat;
+use crate::test_fixtures::{test_cfg, test_offline_change, test_runner, test_runner_set};
+
+fn get_test_cfg(legacy_template_fields: bool) -> Config {
+    Config {
+        runner_sets: vec![RunnerSetConfig {
+            webhook: WebhookOptions {
+                headers: vec![(String::from("X-Event-Id"), String::from("{{ event.id }}"))],
+                ..WebhookOptions::default()
+            },
+            ..test_runner_set("my-org")
+        }],
+        format: Some(TemplateFormat::GenericJson),
+        legacy_template_fields,
+        ..test_cfg()
+    }
+}
+
+#[test]
+fn event_schema_test() {
+    // the committed schema needs to be regenerated whenever the event types change
+    assert_eq!(
+        include_str!("../../event.schema.json"),
+        event_schema(),
+        "run `cargo run -- event-schema > event.schema.json`"
+    );
+
+    // every event has the required fields of the schema
+    let schema: Value = serde_json::from_str(&event_schema()).unwrap();
+    let cfg = get_test_cfg(true);
+    let event = serde_json::to_value(Event::new(
+        &cfg,
+        &test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false)),
+    ))
+    .unwrap();
+    for field in schema["required"].as_array().unwrap() {
+        assert!(event.get(field.as_str().unwrap()).is_some(), "{}", field);
+    }
+    assert_eq!(
+        schema["$defs"]["EventKind"]["enum"],
+        serde_json::json!(["created", "removed", "online", "offline"])
+    );
+}
+
+#[test]
+fn event_id_test() {
+    let cfg = get_test_cfg(true);
+    let change = test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false));
+    let event = Event::new(&cfg, &change);
+    // every handler gets the same id for the same change
+    assert_eq!(event.id, Event::new(&cfg, &change).id);
+    assert_eq!(event.id.len(), 36);
+
+    let name = &cfg.runner_sets[0].name;
+    let mut later = test_runner(name, 42, false);
+    later.utc_ping_time = String::from("2024-05-01T12:05:00+00:00");
+    let next_scan = RunnerStateChange::Offline(test_runner(name, 42, true), later.clone());
+    assert_ne!(event.id, Event::new(&cfg, &next_scan).id);
+    let removed = RunnerStateChange::Removed(later);
+    assert_eq!(Event::new(&cfg, &removed).kind, EventKind::Removed);
+    assert_ne!(
+        Event::new(&cfg, &next_scan).id,
+        Event::new(&cfg, &removed).id
+    );
+}
+
+#[test]
+fn event_template_test() {
+    for legacy_template_fields in [true, false] {
+        let cfg = get_test_cfg(legacy_template_fields);
+        let change = test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false));
+        let alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+        let request = alert_handler.render_alert(&cfg, &change).unwrap();
+        let body: Value = serde_json::from_str(&request.body).unwrap();
+        let event = Event::new(&cfg, &change);
+        assert_eq!(body["event"]["id"], event.id);
+        assert_eq!(body["event"]["runner"]["name"], "runner-42");
+        assert_eq!(body["event"]["previous_runner"]["online"], true);
+        assert_eq!(request.webhook.headers[0].1, event.id);
+        // the built-in templates don't need the legacy fields
+        assert_eq!(body["title"], "Runner runner-42 went offline");
+        assert_eq!(body["new_runner"].is_null(), !legacy_template_fields);
+    }
+
+    // templates using the Runner structs need the legacy fields
+    let mut cfg = get_test_cfg(false);
+    cfg.offline_template_path = Some(String::from("./src/tests/test_offline_template.txt.j2"));
+    let alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    assert!(alert_handler
+        .render_alert(
+            &cfg,
+            &test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false))
+        )
+        .is_err());
+    cfg.legacy_template_fields = true;
+    let alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    alert_handler
+        .render_alert(
+            &cfg,
+            &test_offline_change(test_runner(&cfg.runner_sets[0].name, 42, false)),
+        )
+        .unwrap();
+}
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+
+use crate::history::{
+    append_history, compute_uptime, format_uptime_report, load_history, HistoryEntry,
+};
+use crate::test_fixtures::temp_path;
+
+fn entry(time: &str, id: i64, kind: &str, online: Option<bool>, label: &str) -> HistoryEntry {
+    HistoryEntry {
+        utc_time: String::from(time),
+        kind: String::from(kind),
+        runner_set: String::from("org: chrisTestOrg; github: https://github.com/api/v3"),
+        runner_id: id,
+        runner_name: format!("runner-{}", id),
+        labels: vec![String::from(label)],
+        online,
+    }
+}
+
+fn time(time: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(time).unwrap().into()
+}
+
+#[test]
+fn compute_uptime_test() {
+    let entries = vec![
+        // online for the first 6 hours, offline for 6 hours and online again
+        entry("2024-05-01T00:00:00Z", 1, "observed", Some(true), "gpu"),
+        entry("2024-05-01T06:00:00Z", 1, "offline", Some(false), "gpu"),
+        entry("2024-05-01T12:00:00Z", 1, "online", Some(true), "gpu"),
+        // duplicate alerts from retries don't change anything
+        entry("2024-05-01T12:00:01Z", 1, "online", Some(true), "gpu"),
+        // created at noon and removed 6 hours later
+        entry("2024-05-01T12:00:00Z", 2, "created", Some(true), "cpu"),
+        entry("2024-05-01T18:00:00Z", 2, "removed", None, "cpu"),
+    ];
+    let report = compute_uptime(
+        &entries,
+        time("2024-05-01T00:00:00Z"),
+        time("2024-05-02T00:00:00Z"),
+    )
+    .unwrap();
+
+    assert_eq!(report.runners.len(), 2);
+    assert_eq!(report.runners[0].online_seconds, 18 * 3600);
+    assert_eq!(report.runners[0].observed_seconds, 24 * 3600);
+    assert_eq!(report.runners[0].uptime_percent, Some(75.0));
+    assert_eq!(report.runners[1].online_seconds, 6 * 3600);
+    assert_eq!(report.runners[1].observed_seconds, 6 * 3600);
+    assert_eq!(report.runners[1].uptime_percent, Some(100.0));
+
+    assert_eq!(report.label_pools.len(), 2);
+    assert_eq!(report.label_pools[0].name, "cpu");
+    assert_eq!(report.label_pools[0].uptime_percent, Some(100.0));
+    assert_eq!(report.label_pools[1].name, "gpu");
+    assert_eq!(report.label_pools[1].uptime_percent, Some(75.0));
+
+    assert_eq!(report.runner_sets.len(), 1);
+    assert_eq!(report.runner_sets[0].online_seconds, 24 * 3600);
+    assert_eq!(report.runner_sets[0].observed_seconds, 30 * 3600);
+    assert_eq!(report.runner_sets[0].uptime_percent, Some(80.0));
+
+    // only the window is considered
+    let report = compute_uptime(
+        &entries,
+        time("2024-05-01T03:00:00Z"),
+        time("2024-05-01T09:00:00Z"),
+    )
+    .unwrap();
+    assert_eq!(report.runners[0].uptime_percent, Some(50.0));
+    assert_eq!(report.runners[1].uptime_percent, None);
+    assert!(format_uptime_report(&report).contains("50.00%  runner-1 #1"));
+}
+
+#[test]
+fn history_file_test() {
+    let path = &temp_path("history");
+    let _ = std::fs::remove_file(path);
+
+    // a missing history file is an empty history
+    assert_eq!(load_history(path).unwrap(), vec![]);
+
+    let first = vec![entry(
+        "2024-05-01T00:00:00Z",
+        1,
+        "observed",
+        Some(true),
+        "gpu",
+    )];
+    let second = vec![
+        entry("2024-05-01T06:00:00Z", 1, "offline", Some(false), "gpu"),
+        entry("2024-05-01T18:00:00Z", 2, "removed", None, "cpu"),
+    ];
+    append_history(path, &first).unwrap();
+    append_history(path, &second).unwrap();
+    assert_eq!(load_history(path).unwrap(), [first, second].concat());
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,19 @@
+use crate::logging::{read_log_settings, LogFormat};
+use crate::test_fixtures::write_temp_cfg;
+
+#[test]
+fn read_log_settings_test() {
+    let path = write_temp_cfg(
+        "log_settings",
+        "grace_period: 0\nlog_level: debug\nlog_format: json\norgs: []\n",
+    );
+    let settings = read_log_settings(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(settings.log_level.as_deref(), Some("debug"));
+    assert_eq!(settings.log_format, Some(LogFormat::Json));
+
+    // problems are reported once the config gets parsed
+    let settings = read_log_settings("/nonexistent/config.yaml");
+    assert_eq!(settings.log_level, None);
+    assert_eq!(settings.log_format, None);
+}
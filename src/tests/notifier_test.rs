@@ -0,0 +1,251 @@
+use axum::{
+    extract::{Path, State},
+    http::{header::RETRY_AFTER, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::Arc, time::Instant};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+use crate::alert::AlertHandler;
+use crate::config::parse_cfg;
+use crate::inbound_alert_handler::InboundAlertHandler;
+use crate::notifier::{alert_title, truncate, NotifierAlertHandler};
+use crate::signature::SIGNATURE_HEADER;
+use crate::structs::{
+    Config, Notifier, Runner, RunnerSetConfig, RunnerStateChange, WebhookOptions,
+};
+use crate::template::TemplateFormat;
+use crate::test_fixtures::{
+    test_cfg, test_offline_change, test_runner, test_runner_set, write_temp_cfg,
+};
+
+// path -> every request received, including the rate limited ones
+type Received = Arc<Mutex<HashMap<String, Vec<(HeaderMap, Value)>>>>;
+
+// the first request to /discord and /mattermost is rate limited the way each platform does it,
+// /limited is rate limited forever, /slow asks for a longer wait than the inbound_timeout
+async fn chat_mock(address: &str) -> Received {
+    let received = Received::default();
+    let app = Router::new()
+        .route(
+            "/:path",
+            post(
+                |State(received): State<Received>,
+                 Path(path): Path<String>,
+                 headers: HeaderMap,
+                 Json(body): Json<Value>| async move {
+                    let mut received = received.lock().await;
+                    let requests = received.entry(path.clone()).or_default();
+                    requests.push((headers, body));
+                    match (path.as_str(), requests.len()) {
+                        ("discord", 1) => {
+                            let body = json!({"retry_after": 0.05, "global": false});
+                            (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response()
+                        }
+                        ("mattermost", 1) | ("limited", _) => {
+                            (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, "0")]).into_response()
+                        }
+                        ("slow", _) => {
+                            (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, "60")]).into_response()
+                        }
+                        _ => StatusCode::NO_CONTENT.into_response(),
+                    }
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = TcpListener::bind(address).await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    received
+}
+
+fn get_test_runner_set(slug: &str, notifier: Notifier, path: &str) -> RunnerSetConfig {
+    RunnerSetConfig {
+        webhook_endpoint: format!("http://127.0.0.1:9014/{}", path),
+        // as parsed with `format: generic-json` and a webhook_signing_secret
+        webhook: WebhookOptions {
+            content_type: Some(String::from("application/json")),
+            signing_secret: Some(String::from("some_secret")),
+            ..WebhookOptions::default()
+        },
+        format: notifier.format(),
+        notifier,
+        ..test_runner_set(slug)
+    }
+}
+
+fn get_test_cfg() -> Config {
+    Config {
+        runner_sets: vec![
+            get_test_runner_set("webhook-org", Notifier::Webhook, "webhook"),
+            get_test_runner_set("discord-org", Notifier::Discord, "discord"),
+            get_test_runner_set("mattermost-org", Notifier::Mattermost, "mattermost"),
+            get_test_runner_set("limited-org", Notifier::Discord, "limited"),
+            get_test_runner_set("slow-org", Notifier::Mattermost, "slow"),
+        ],
+        allow_http: true,
+        format: Some(TemplateFormat::GenericJson),
+        ..test_cfg()
+    }
+}
+
+#[tokio::test]
+async fn notifier_test() {
+    let received = chat_mock("127.0.0.1:9014").await;
+    let cfg = get_test_cfg();
+    let mut alert_handler = NotifierAlertHandler::new(InboundAlertHandler::new(&cfg).unwrap());
+    // a runner name that would ping everyone on Discord unless escaped
+    let offline_change = |runner_set: usize| {
+        test_offline_change(Runner {
+            name: String::from("runner_<@everyone>"),
+            webhook_endpoint: cfg.runner_sets[runner_set].webhook_endpoint.clone(),
+            ..test_runner(&cfg.runner_sets[runner_set].name, 42, false)
+        })
+    };
+    for runner_set in 0..3 {
+        alert_handler
+            .send_alert(&cfg, offline_change(runner_set))
+            .await
+            .unwrap();
+    }
+    let e = alert_handler
+        .send_alert(&cfg, offline_change(3))
+        .await
+        .unwrap_err();
+    assert!(
+        format!("{:#}", e).contains("webhook request returned status 429"),
+        "{:#}",
+        e
+    );
+    // the next scan retries instead of waiting longer than the inbound_timeout
+    let start = Instant::now();
+    let e = alert_handler
+        .send_alert(&cfg, offline_change(4))
+        .await
+        .unwrap_err();
+    assert!(
+        start.elapsed() < cfg.inbound_timeout * 2,
+        "{:?}",
+        start.elapsed()
+    );
+    assert!(format!("{:#}", e).contains("status 429"), "{:#}", e);
+
+    let received = received.lock().await;
+    let bodies = |path: &str| -> Vec<&Value> { received[path].iter().map(|(_, b)| b).collect() };
+    // the webhook gets the template
+    assert_eq!(bodies("webhook").len(), 1);
+    assert_eq!(bodies("webhook")[0]["kind"], "offline");
+    // the rate limited requests are sent again
+    assert_eq!(bodies("discord").len(), 2);
+    assert_eq!(bodies("discord")[0], bodies("discord")[1]);
+    assert_eq!(bodies("mattermost").len(), 2);
+    // the first try and three retries
+    assert_eq!(bodies("limited").len(), 4);
+    // the first try and one retry once the inbound_timeout is used up
+    assert_eq!(bodies("slow").len(), 2);
+
+    // the chat notifiers are webhooks with their own built-in format, so they are signed as well
+    for path in ["webhook", "discord", "mattermost"] {
+        assert!(
+            received[path][0].0.contains_key(SIGNATURE_HEADER),
+            "{}",
+            path
+        );
+    }
+
+    let embed = &bodies("discord")[0]["embeds"][0];
+    // names are escaped, so they can't format the message
+    assert_eq!(
+        embed["title"],
+        "Runner runner\\_\\<@everyone\\> went offline"
+    );
+    assert_eq!(embed["color"], 0xE01E5A);
+    assert_eq!(
+        embed["url"],
+        "https://github.com/organizations/discord-org/settings/actions/runners"
+    );
+    assert!(embed["description"].as_str().unwrap().contains("OS: linux"));
+    // nobody gets pinged by a runner name
+    assert_eq!(
+        bodies("discord")[0]["allowed_mentions"],
+        json!({"parse": []})
+    );
+
+    let attachment = &bodies("mattermost")[0]["attachments"][0];
+    assert_eq!(attachment["color"], "#E01E5A");
+    assert!(attachment["text"].as_str().unwrap().contains("OS: linux"));
+}
+
+#[test]
+fn alert_title_test() {
+    let name = &test_runner_set("some-org").name;
+    let (online, offline) = (test_runner(name, 42, true), test_runner(name, 42, false));
+    for (change, title) in [
+        (
+            RunnerStateChange::Created(online.clone()),
+            "Runner runner-42 was created",
+        ),
+        (
+            RunnerStateChange::Removed(offline.clone()),
+            "Runner runner-42 was removed",
+        ),
+        (
+            RunnerStateChange::Online(offline.clone(), online.clone()),
+            "Runner runner-42 is back online",
+        ),
+        (
+            RunnerStateChange::Offline(online, offline),
+            "Runner runner-42 went offline",
+        ),
+    ] {
+        assert_eq!(alert_title(&change), title);
+    }
+
+    assert_eq!(truncate("runner", 10), "runner");
+    assert_eq!(truncate("runner-with-a-long-name", 10), "runner-wi…");
+}
+
+#[test]
+fn notifier_cfg_test() {
+    let cfg_path = write_temp_cfg(
+        "notifier_cfg",
+        "grace_period: 0
+format: slack
+orgs:
+  - name: someOrg
+    github_base_uri: https://api.github.com
+    github_pat: some_pat
+    webhook_endpoint: https://discord.com/api/webhooks/1/token
+    notifier: discord
+  - name: otherOrg
+    github_base_uri: https://api.github.com
+    github_pat: some_pat
+    webhook_endpoint: https://mattermost.example.com/hooks/token
+    notifier: mattermost
+  - name: slackOrg
+    github_base_uri: https://api.github.com
+    github_pat: some_pat
+    webhook_endpoint: https://hooks.slack.com/services/token
+",
+    );
+    let cfg = parse_cfg(&cfg_path, false).unwrap();
+    std::fs::remove_file(&cfg_path).unwrap();
+    let notifiers: Vec<_> = cfg
+        .runner_sets
+        .iter()
+        .map(|s| (s.notifier, s.format))
+        .collect();
+    // the chat notifiers use the matching built-in format instead of the global one
+    assert_eq!(
+        notifiers,
+        vec![
+            (Notifier::Discord, Some(TemplateFormat::Discord)),
+            (Notifier::Mattermost, Some(TemplateFormat::Mattermost)),
+            (Notifier::Webhook, None),
+        ]
+    );
+}
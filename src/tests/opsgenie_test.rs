@@ -0,0 +1,144 @@
+use axum::{
+    extract::{RawQuery, State},
+    http::{HeaderMap, StatusCode, Uri},
+    routing::post,
+    Json, Router,
+};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+use crate::alert::AlertHandler;
+use crate::config::parse_cfg;
+use crate::opsgenie::{opsgenie_alias, OpsgenieAlertHandler};
+use crate::structs::{
+    Config, Notifier, RunnerSetConfig, RunnerStateChange, WebhookAuth, WebhookOptions,
+};
+use crate::test_fixtures::{test_cfg, test_runner, test_runner_set, write_temp_cfg};
+
+#[derive(Debug)]
+struct ReceivedRequest {
+    path: String,
+    query: Option<String>,
+    authorization: String,
+    body: Value,
+}
+
+type Received = Arc<Mutex<Vec<ReceivedRequest>>>;
+
+// stands in for the Opsgenie alert API
+async fn opsgenie_mock(address: &str) -> Received {
+    let received = Received::default();
+    let handler = |State(received): State<Received>,
+                   uri: Uri,
+                   RawQuery(query): RawQuery,
+                   headers: HeaderMap,
+                   Json(body): Json<Value>| async move {
+        received.lock().await.push(ReceivedRequest {
+            path: uri.path().to_string(),
+            query,
+            authorization: headers["authorization"].to_str().unwrap().to_string(),
+            body,
+        });
+        StatusCode::ACCEPTED
+    };
+    let app = Router::new()
+        .route("/v2/alerts", post(handler))
+        .route("/v2/alerts/:alias/close", post(handler))
+        .with_state(received.clone());
+    let listener = TcpListener::bind(address).await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    received
+}
+
+fn get_test_cfg() -> Config {
+    Config {
+        runner_sets: vec![RunnerSetConfig {
+            webhook_endpoint: String::from("http://127.0.0.1:9016/"),
+            webhook: WebhookOptions {
+                auth: Some(WebhookAuth::GenieKey(String::from("genie_secret"))),
+                ..WebhookOptions::default()
+            },
+            notifier: Notifier::Opsgenie,
+            ..test_runner_set("my-org")
+        }],
+        allow_http: true,
+        ..test_cfg()
+    }
+}
+
+#[tokio::test]
+async fn opsgenie_test() {
+    let received = opsgenie_mock("127.0.0.1:9016").await;
+    let cfg = get_test_cfg();
+    let name = &cfg.runner_sets[0].name;
+    let (online, offline) = (test_runner(name, 42, true), test_runner(name, 42, false));
+    let mut alert_handler = OpsgenieAlertHandler;
+    for change in [
+        // nothing to close yet
+        RunnerStateChange::Created(online.clone()),
+        RunnerStateChange::Offline(online.clone(), offline.clone()),
+        RunnerStateChange::Removed(offline.clone()),
+    ] {
+        alert_handler.send_alert(&cfg, change).await.unwrap();
+    }
+
+    let received = received.lock().await;
+    assert_eq!(received.len(), 2);
+    let alias = opsgenie_alias(&offline);
+    // stable, and usable in a path
+    assert_eq!(alias, opsgenie_alias(&online));
+    assert!(alias.starts_with("gh_runner_uptime-"));
+    assert_eq!(alias.len(), "gh_runner_uptime-".len() + 64);
+
+    let create = &received[0];
+    assert_eq!(create.path, "/v2/alerts");
+    assert_eq!(create.authorization, "GenieKey genie_secret");
+    assert_eq!(create.body["alias"], alias);
+    assert_eq!(create.body["message"], "Runner runner-42 went offline");
+    assert_eq!(create.body["entity"], "my-org");
+    assert_eq!(
+        create.body["details"]["runner_key"],
+        format!("{}; runner id: 42", name)
+    );
+
+    let close = &received[1];
+    assert_eq!(close.path, format!("/v2/alerts/{}/close", alias));
+    assert_eq!(close.query.as_deref(), Some("identifierType=alias"));
+    assert_eq!(close.authorization, "GenieKey genie_secret");
+    assert_eq!(close.body["note"], "Runner runner-42 was removed");
+}
+
+#[test]
+fn opsgenie_cfg_test() {
+    let runner_set = "grace_period: 0
+orgs:
+  - name: someOrg
+    github_base_uri: https://api.github.com
+    github_pat: some_pat
+    webhook_endpoint: https://api.eu.opsgenie.com
+    notifier: opsgenie
+";
+    // no templates are needed
+    let cfg_path = write_temp_cfg(
+        "opsgenie_cfg",
+        &format!("{}    opsgenie_api_key: genie_secret\n", runner_set),
+    );
+    let cfg = parse_cfg(&cfg_path, false).unwrap();
+    assert_eq!(
+        cfg.runner_sets[0].webhook.auth,
+        Some(WebhookAuth::GenieKey(String::from("genie_secret")))
+    );
+    assert!(!format!("{:?}", cfg).contains("genie_secret"));
+    assert_eq!(crate::check::validate_cfg(&cfg), Vec::<String>::new());
+
+    std::fs::write(&cfg_path, runner_set).unwrap();
+    let e = parse_cfg(&cfg_path, false).unwrap_err();
+    assert!(
+        format!("{:#}", e).contains("the opsgenie notifier needs opsgenie_api_key"),
+        "{:#}",
+        e
+    );
+    std::fs::remove_file(&cfg_path).unwrap();
+}
@@ -0,0 +1,81 @@
+use axum::{http::HeaderMap, routing::get, Router};
+use reqwest::ClientBuilder;
+use tokio::net::TcpListener;
+
+use crate::proxy::{apply_proxy, ProxyConfig};
+
+// answers with who received the request and the proxy credentials
+async fn mock(address: &str, name: &'static str) {
+    let app = Router::new().route(
+        "/webhook",
+        get(move |headers: HeaderMap| async move {
+            let auth = headers
+                .get("proxy-authorization")
+                .map(|h| h.to_str().unwrap().to_string())
+                .unwrap_or_default();
+            format!("{} {}", name, auth)
+        }),
+    );
+    let listener = TcpListener::bind(address).await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+}
+
+async fn get_via(proxy: Option<&ProxyConfig>, url: &str) -> String {
+    let client = apply_proxy(ClientBuilder::new(), proxy)
+        .unwrap()
+        .build()
+        .unwrap();
+    client.get(url).send().await.unwrap().text().await.unwrap()
+}
+
+#[tokio::test]
+async fn proxy_test() {
+    mock("127.0.0.1:9012", "proxy").await;
+    mock("127.0.0.1:9013", "direct").await;
+
+    let proxy = ProxyConfig {
+        url: String::from("http://127.0.0.1:9012"),
+        username: Some(String::from("monitor")),
+        password: Some(String::from("proxy_secret")),
+        no_proxy: vec![String::from("127.0.0.1")],
+    };
+    // the proxy doesn't resolve the host, the mock answers for any host
+    assert_eq!(
+        get_via(Some(&proxy), "http://webhook.invalid/webhook").await,
+        "proxy Basic bW9uaXRvcjpwcm94eV9zZWNyZXQ="
+    );
+    // hosts on the no_proxy list are connected to directly
+    assert_eq!(
+        get_via(Some(&proxy), "http://127.0.0.1:9013/webhook").await,
+        "direct "
+    );
+    let direct = ProxyConfig {
+        url: String::from("direct"),
+        username: None,
+        password: None,
+        no_proxy: vec![],
+    };
+    assert_eq!(
+        get_via(Some(&direct), "http://127.0.0.1:9013/webhook").await,
+        "direct "
+    );
+
+    let invalid = ProxyConfig {
+        url: String::from("not a url"),
+        ..direct
+    };
+    assert!(apply_proxy(ClientBuilder::new(), Some(&invalid)).is_err());
+    // reqwest can't authenticate with socks4 proxies, so they are rejected
+    for url in [
+        "socks4://monitor@127.0.0.1:1080",
+        "socks4a://127.0.0.1:1080",
+    ] {
+        let socks4 = ProxyConfig {
+            url: String::from(url),
+            username: Some(String::from("monitor")),
+            ..invalid.clone()
+        };
+        let e = apply_proxy(ClientBuilder::new(), Some(&socks4)).unwrap_err();
+        assert!(e.to_string().starts_with("Unsupported proxy scheme socks4"));
+    }
+}
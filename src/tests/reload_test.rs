@@ -0,0 +1,131 @@
+use axum::{extract::Path, routing::get, Json, Router};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+
+use crate::config::parse_cfg;
+use crate::github::get_all_runners;
+use crate::scan::Scanner;
+use crate::structs::MonitorState;
+use crate::test_fixtures::write_temp_cfg;
+
+// every org has one online runner with the same id as the org name's length
+async fn github_mock(address: &str) {
+    let app = Router::new().route(
+        "/orgs/:org/actions/runners",
+        get(|Path(org): Path<String>| async move {
+            Json::<Value>(json!({
+                "total_count": 1,
+                "runners": [{
+                    "id": org.len(),
+                    "name": format!("{}-runner", org),
+                    "os": "linux",
+                    "status": "online",
+                    "busy": false,
+                    "labels": []
+                }]
+            }))
+        }),
+    );
+    let listener = TcpListener::bind(address).await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+}
+
+fn write_cfg(name: &str, orgs: &[(&str, &str)], template: &str) -> String {
+    let mut yaml = format!(
+        "grace_period: 0
+created_template_path: {}
+removed_template_path: src/tests/test_removed_template.txt.j2
+online_template_path: src/tests/test_online_template.txt.j2
+offline_template_path: src/tests/test_offline_template.txt.j2
+orgs:
+",
+        template
+    );
+    for (org, webhook) in orgs {
+        yaml.push_str(&format!(
+            "  - name: {}
+    github_base_uri: http://127.0.0.1:9008
+    github_pat: some_pat
+    webhook_endpoint: {}
+",
+            org, webhook
+        ));
+    }
+    write_temp_cfg(name, &yaml)
+}
+
+#[tokio::test]
+async fn reload_test() {
+    github_mock("127.0.0.1:9008").await;
+    let template = "src/tests/test_created_template.txt.j2";
+    let first_path = write_cfg(
+        "first",
+        &[
+            ("keptOrg", "https://old.example.com"),
+            ("removedOrg", "https://example.com"),
+        ],
+        template,
+    );
+    let cfg = Arc::new(parse_cfg(&first_path, true).unwrap());
+    let runners = get_all_runners(&cfg, true).await.unwrap();
+    let state = Arc::new(RwLock::new(MonitorState::new(&cfg, runners)));
+    let scanner = Scanner::new(cfg, state.clone()).unwrap();
+
+    let kept_key = "org: keptOrg; github: http://127.0.0.1:9008; runner id: 7";
+    let kept_since = state.read().await.state_since[kept_key];
+    // pretend the runner is in its grace period
+    state
+        .write()
+        .await
+        .runners
+        .get_mut(kept_key)
+        .unwrap()
+        .online_state_change_since = 2;
+
+    // a broken config is rejected and changes nothing
+    let broken_path = write_cfg(
+        "broken",
+        &[("addedOrg", "https://example.com")],
+        "missing.j2",
+    );
+    assert!(scanner.reload(&broken_path).await.is_err());
+    assert_eq!(scanner.cfg().runner_sets.len(), 2);
+    assert_eq!(state.read().await.runners.len(), 2);
+
+    let second_path = write_cfg(
+        "second",
+        &[
+            ("keptOrg", "https://new.example.com"),
+            ("addedOrg", "https://example.com"),
+        ],
+        template,
+    );
+    // no alerts are sent; the webhooks don't exist
+    scanner.reload(&second_path).await.unwrap();
+    for path in [first_path, broken_path, second_path] {
+        std::fs::remove_file(path).unwrap();
+    }
+
+    let cfg = scanner.cfg();
+    assert_eq!(cfg.runner_sets.len(), 2);
+    let state = state.read().await;
+    assert_eq!(state.runners.len(), 2);
+    assert!(!state
+        .runner_sets
+        .contains_key("org: removedOrg; github: http://127.0.0.1:9008"));
+
+    // the state of unchanged runner sets is kept
+    let kept = &state.runners[kept_key];
+    assert_eq!(kept.online_state_change_since, 2);
+    assert_eq!(kept.webhook_endpoint, "https://new.example.com");
+    assert_eq!(state.state_since[kept_key], kept_since);
+
+    // new runner sets are initialized like on startup
+    let added = &state.runners["org: addedOrg; github: http://127.0.0.1:9008; runner id: 8"];
+    assert_eq!(added.interpret_online, Some(true));
+    assert!(state
+        .runner_sets
+        .contains_key("org: addedOrg; github: http://127.0.0.1:9008"));
+}
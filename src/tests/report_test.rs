@@ -0,0 +1,186 @@
+use axum::{extract::State, routing::post, Router};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::github::key_runner;
+use crate::history::{append_history, HistoryEntry};
+use crate::report::{compute_runner_set_report, next_report_time, send_reports};
+use crate::structs::{Config, MonitorState, ReportInterval, Runner, RunnerSetConfig};
+use crate::template::Templates;
+use crate::test_fixtures::{temp_path, test_cfg_with_templates, test_runner_set};
+
+const RUNNER_SET: &str = "org: chrisTestOrg; github: https://github.com/api/v3";
+
+fn entry(time: &str, id: i64, kind: &str, online: Option<bool>) -> HistoryEntry {
+    HistoryEntry {
+        utc_time: String::from(time),
+        kind: String::from(kind),
+        runner_set: String::from(RUNNER_SET),
+        runner_id: id,
+        runner_name: format!("runner-{}", id),
+        labels: vec![String::from("some label")],
+        online,
+    }
+}
+
+fn time(time: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(time).unwrap().into()
+}
+
+fn get_test_history() -> Vec<HistoryEntry> {
+    vec![
+        entry("2024-04-30T00:00:00Z", 1, "observed", Some(true)),
+        // short outage
+        entry("2024-05-01T01:00:00Z", 1, "offline", Some(false)),
+        entry("2024-05-01T02:00:00Z", 1, "online", Some(true)),
+        // long outage, still ongoing
+        entry("2024-05-01T10:00:00Z", 1, "offline", Some(false)),
+        // retried alert
+        entry("2024-05-01T10:01:00Z", 1, "offline", Some(false)),
+        // outage that started before the report period
+        entry("2024-04-30T00:00:00Z", 2, "observed", Some(false)),
+        entry("2024-05-01T03:00:00Z", 2, "removed", None),
+        entry("2024-05-01T12:00:00Z", 3, "created", Some(true)),
+    ]
+}
+
+fn get_test_state() -> MonitorState {
+    let mut state = MonitorState::default();
+    for (id, online) in [(1, false), (3, true)] {
+        let (key, runner) = key_runner(Runner {
+            utc_ping_time: String::from("1"),
+            interpret_online: Some(online),
+            online_for_github_api: online,
+            busy: false,
+            online_state_change_since: 0,
+            runner_set: String::from(RUNNER_SET),
+            id,
+            name: format!("runner-{}", id),
+            os: String::from("linux"),
+            labels: vec![String::from("some label")],
+            webhook_endpoint: String::new(),
+            webhook_cloudevents: None,
+        });
+        state.runners.insert(key, runner);
+    }
+    state
+}
+
+#[test]
+fn next_report_time_test() {
+    // a Wednesday
+    let now = time("2024-05-01T13:37:00Z");
+    assert_eq!(
+        next_report_time(ReportInterval::Daily, now),
+        time("2024-05-02T00:00:00Z")
+    );
+    assert_eq!(
+        next_report_time(ReportInterval::Weekly, now),
+        time("2024-05-06T00:00:00Z")
+    );
+    // exactly at midnight on a Monday the next report is a week later
+    assert_eq!(
+        next_report_time(ReportInterval::Weekly, time("2024-05-06T00:00:00Z")),
+        time("2024-05-13T00:00:00Z")
+    );
+}
+
+#[test]
+fn compute_runner_set_report_test() {
+    let report = compute_runner_set_report(
+        RUNNER_SET,
+        &get_test_state(),
+        &get_test_history(),
+        time("2024-05-01T00:00:00Z"),
+        time("2024-05-02T00:00:00Z"),
+    );
+    assert_eq!(report.runner_count, 2);
+    assert_eq!(report.offline_runners.len(), 1);
+    assert_eq!(report.offline_runners[0].runner.id, 1);
+
+    assert_eq!(report.longest_outages.len(), 3);
+    assert_eq!(report.longest_outages[0].runner.id, 1);
+    assert_eq!(report.longest_outages[0].duration_seconds, 14 * 3600);
+    assert_eq!(report.longest_outages[0].end, None);
+    assert_eq!(report.longest_outages[1].runner.id, 2);
+    assert_eq!(report.longest_outages[1].duration_seconds, 3 * 3600);
+    assert_eq!(report.longest_outages[2].duration, "1h 0m");
+
+    assert_eq!(report.flappiest_runners.len(), 1);
+    assert_eq!(report.flappiest_runners[0].runner.id, 1);
+    assert_eq!(report.flappiest_runners[0].state_changes, 4);
+    assert_eq!(report.created_runners.len(), 1);
+    assert_eq!(report.created_runners[0].runner.id, 3);
+    assert_eq!(report.removed_runners.len(), 1);
+    assert_eq!(report.removed_runners[0].runner.id, 2);
+}
+
+#[tokio::test]
+async fn send_reports_test() {
+    let history_path = &temp_path("report_history");
+    let _ = std::fs::remove_file(history_path);
+    append_history(history_path, &get_test_history()).unwrap();
+
+    let received = Arc::new(Mutex::new(vec![]));
+    let app = Router::new()
+        .route(
+            "/webhook",
+            post(
+                |State(received): State<Arc<Mutex<Vec<String>>>>, body: String| async move {
+                    received.lock().await.push(body);
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = TcpListener::bind("127.0.0.1:9006").await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let cfg = Config {
+        runner_sets: vec![
+            // answers 404
+            RunnerSetConfig {
+                webhook_endpoint: String::from("http://127.0.0.1:9006/missing"),
+                ..test_runner_set("broken-org")
+            },
+            RunnerSetConfig {
+                name: String::from(RUNNER_SET),
+                webhook_endpoint: String::from("http://127.0.0.1:9006/webhook"),
+                ..test_runner_set("test-org")
+            },
+        ],
+        allow_http: true,
+        history_path: Some(String::from(history_path)),
+        report_interval: Some(ReportInterval::Daily),
+        report_template_path: Some(String::from("src/tests/test_report_template.txt.j2")),
+        ..test_cfg_with_templates()
+    };
+    let e = send_reports(
+        &cfg,
+        Arc::new(Templates::new(&cfg).unwrap()),
+        &Arc::new(RwLock::new(get_test_state())),
+        time("2024-05-01T00:00:00Z"),
+        time("2024-05-02T00:00:00Z"),
+    )
+    .await
+    .unwrap_err();
+    std::fs::remove_file(history_path).unwrap();
+    // the failed runner set doesn't keep the others from their reports
+    assert_eq!(
+        e.to_string(),
+        format!("Failed to send the reports of {}", cfg.runner_sets[0].name)
+    );
+
+    let received = received.lock().await;
+    assert_eq!(received.len(), 1);
+    assert_eq!(
+        received[0],
+        r#"[{
+    "summary": "Runner report for org: chrisTestOrg; github: https://github.com/api/v3: 1 of 2 runners offline",
+    "event_body": "Period: 2024-05-01T00:00:00+00:00 - 2024-05-02T00:00:00+00:00\nOffline: runner-1 since unknown\nOutage: runner-1 for 14h 0m\nOutage: runner-2 for 3h 0m\nOutage: runner-1 for 1h 0m\nFlapping: runner-1 changed state 4 times\nCreated: runner-3\nRemoved: runner-2",
+    "type_field": "github_monitor"
+}]
+"#
+    );
+}
@@ -0,0 +1,193 @@
+use axum::{extract::State, routing::get, routing::post, Json, Router};
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+use tokio::time::sleep;
+
+use crate::github::{get_github_client, get_github_org_endpoint, key_runner};
+use crate::scan::Scanner;
+use crate::status_api::{start_status_api, StatusApiState};
+use crate::structs::{Config, MonitorState, Runner, RunnerMap, RunnerSetConfig};
+use crate::test_fixtures::{test_cfg_with_templates, test_runner_set};
+use crate::tls::TlsConfig;
+
+// pretends to be GitHub and the webhook at the same time
+// GitHub always reports runner 69 to be offline
+async fn github_and_inbound_mock(address: &str, github_requests: Arc<AtomicUsize>) {
+    async fn runners(State(github_requests): State<Arc<AtomicUsize>>) -> Json<Value> {
+        github_requests.fetch_add(1, Ordering::SeqCst);
+        // make the scan take a while so that triggers can pile up
+        sleep(Duration::from_millis(200)).await;
+        Json(json!({
+            "total_count": 1,
+            "runners": [{
+                "id": 69,
+                "name": "runner-01",
+                "os": "linux",
+                "status": "offline",
+                "busy": false,
+                "labels": [{ "id": 1, "name": "some label", "type": "custom" }]
+            }]
+        }))
+    }
+    let app = Router::new()
+        .route("/orgs/chrisTestOrg/actions/runners", get(runners))
+        .route("/webhook", post(|| async { "" }))
+        .with_state(github_requests);
+    let listener = TcpListener::bind(address).await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+}
+
+fn get_scan_test_config(mock_address: &str, status_api_address: &str) -> Config {
+    Config {
+        runner_sets: vec![RunnerSetConfig {
+            name: String::from("org: chrisTestOrg; github: mock"),
+            github_endpoint: get_github_org_endpoint(
+                &format!("http://{}", mock_address),
+                "chrisTestOrg",
+            ),
+            webhook_endpoint: format!("http://{}/webhook", mock_address),
+            github_client: get_github_client(
+                Duration::from_millis(1000),
+                "some_pat",
+                &TlsConfig::default(),
+                None,
+                "http://unused",
+                true,
+            )
+            .unwrap()
+            .0,
+            ..test_runner_set("test-org")
+        }],
+        allow_http: true,
+        status_api_address: Some(status_api_address.parse().unwrap()),
+        scan_api_token: Some(String::from("secret_token")),
+        ..test_cfg_with_templates()
+    }
+}
+
+fn get_initial_state(cfg: &Config) -> MonitorState {
+    MonitorState::new(
+        cfg,
+        RunnerMap::from([key_runner(Runner {
+            utc_ping_time: String::from("1"),
+            interpret_online: Some(true),
+            online_for_github_api: true,
+            busy: false,
+            online_state_change_since: 0,
+            runner_set: String::from("org: chrisTestOrg; github: mock"),
+            id: 69,
+            name: String::from("runner-01"),
+            os: String::from("linux"),
+            labels: vec![String::from("some label")],
+            webhook_endpoint: String::new(),
+            webhook_cloudevents: None,
+        })]),
+    )
+}
+
+#[tokio::test]
+async fn scan_api_test() {
+    let github_requests = Arc::new(AtomicUsize::new(0));
+    github_and_inbound_mock("127.0.0.1:9003", github_requests.clone()).await;
+    let cfg = Arc::new(get_scan_test_config("127.0.0.1:9003", "127.0.0.1:9002"));
+    let state = Arc::new(RwLock::new(get_initial_state(&cfg)));
+    start_status_api(
+        cfg.status_api_address.unwrap(),
+        StatusApiState {
+            scanner: Arc::new(Scanner::new(cfg.clone(), state.clone()).unwrap()),
+        },
+    )
+    .await
+    .unwrap();
+
+    let client = Client::new();
+    let resp = client
+        .post("http://127.0.0.1:9002/scan")
+        .bearer_auth("wrong_token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = client
+        .post("http://127.0.0.1:9002/scan?runner_set=unknown")
+        .bearer_auth("secret_token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(github_requests.load(Ordering::SeqCst), 0);
+
+    let resp = client
+        .post("http://127.0.0.1:9002/scan")
+        .query(&[("runner_set", "org: chrisTestOrg; github: mock")])
+        .bearer_auth("secret_token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let report: Value = resp.json().await.unwrap();
+    assert_eq!(
+        report["scanned_runner_sets"],
+        json!(["org: chrisTestOrg; github: mock"])
+    );
+    assert_eq!(report["runner_count"], json!(1));
+    assert_eq!(report["alerts"][0]["kind"], json!("offline"));
+    assert_eq!(report["alerts"][0]["runner_id"], json!(69));
+    assert_eq!(github_requests.load(Ordering::SeqCst), 1);
+
+    let runner = state.read().await.runners.values().next().unwrap().clone();
+    assert_eq!(runner.interpret_online, Some(false));
+}
+
+#[tokio::test]
+async fn scan_coalescing_test() {
+    let github_requests = Arc::new(AtomicUsize::new(0));
+    github_and_inbound_mock("127.0.0.1:9004", github_requests.clone()).await;
+    let cfg = Arc::new(get_scan_test_config("127.0.0.1:9004", "127.0.0.1:9005"));
+    let state = Arc::new(RwLock::new(get_initial_state(&cfg)));
+    let scanner = Scanner::new(cfg, state).unwrap();
+
+    let delayed_scan = || async {
+        // wait for the first scan to be running
+        sleep(Duration::from_millis(50)).await;
+        scanner.scan(None).await.unwrap()
+    };
+    let (first, second, third) = tokio::join!(scanner.scan(None), delayed_scan(), delayed_scan());
+    let first = first.unwrap();
+    assert_eq!(first.alerts.len(), 1);
+    // the second and third trigger arrived during the first scan and share one follow-up scan
+    assert_eq!(second, third);
+    assert!(second.alerts.is_empty());
+    assert_eq!(github_requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn failed_scan_test() {
+    let github_requests = Arc::new(AtomicUsize::new(0));
+    github_and_inbound_mock("127.0.0.1:9023", github_requests.clone()).await;
+    let mut cfg = get_scan_test_config("127.0.0.1:9023", "127.0.0.1:9024");
+    // the webhook answers 404, so the offline alert can't be delivered
+    cfg.runner_sets[0].webhook_endpoint = String::from("http://127.0.0.1:9023/missing");
+    let cfg = Arc::new(cfg);
+    let state = Arc::new(RwLock::new(get_initial_state(&cfg)));
+    let last_scan_time = |state: &MonitorState| {
+        state.runner_sets["org: chrisTestOrg; github: mock"]
+            .last_scan_time
+            .clone()
+    };
+    let initial_scan_time = last_scan_time(&*state.read().await);
+    let scanner = Scanner::new(cfg, state.clone()).unwrap();
+
+    assert!(scanner.scan(None).await.is_err());
+    assert_eq!(github_requests.load(Ordering::SeqCst), 1);
+    // neither the runners nor the time of the last successful scan changed
+    let state = state.read().await;
+    assert_eq!(last_scan_time(&state), initial_scan_time);
+    let runner = state.runners.values().next().unwrap();
+    assert_eq!(runner.interpret_online, Some(true));
+}
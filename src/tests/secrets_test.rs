@@ -0,0 +1,91 @@
+use crate::config::parse_cfg;
+use crate::secrets::interpolate_env;
+use crate::structs::WebhookAuth;
+use crate::test_fixtures::temp_path;
+
+#[test]
+fn interpolate_env_test() {
+    std::env::set_var("GH_RUNNER_UPTIME_TEST_TOKEN", "secret");
+    assert_eq!(
+        interpolate_env("https://example.com?token=${GH_RUNNER_UPTIME_TEST_TOKEN}").unwrap(),
+        "https://example.com?token=secret"
+    );
+    assert_eq!(
+        interpolate_env("$${NOT_A_VAR} costs $5").unwrap(),
+        "${NOT_A_VAR} costs $5"
+    );
+    let e = interpolate_env("${GH_RUNNER_UPTIME_MISSING_VAR}").unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "environment variable GH_RUNNER_UPTIME_MISSING_VAR is not set"
+    );
+    assert!(interpolate_env("${UNTERMINATED").is_err());
+}
+
+#[test]
+fn secrets_in_config_test() {
+    std::env::set_var("GH_RUNNER_UPTIME_TEST_SCAN_TOKEN", "scan_secret");
+    let pat_path = temp_path("pat");
+    let webhook_path = temp_path("webhook");
+    let password_path = temp_path("password");
+    std::fs::write(&pat_path, "pat_secret\n").unwrap();
+    std::fs::write(&webhook_path, "https://example.com?token=webhook_secret\n").unwrap();
+    std::fs::write(&password_path, "password_secret\n").unwrap();
+
+    let cfg_path = temp_path("secrets_cfg.yaml");
+    let yaml = format!(
+        "grace_period: 0
+scan_api_token: ${{GH_RUNNER_UPTIME_TEST_SCAN_TOKEN}}
+created_template_path: src/tests/test_created_template.txt.j2
+removed_template_path: src/tests/test_removed_template.txt.j2
+online_template_path: src/tests/test_online_template.txt.j2
+offline_template_path: src/tests/test_offline_template.txt.j2
+orgs:
+  - name: someOrg
+    github_base_uri: https://github.example.com/api/v3
+    github_pat_file: {}
+    webhook_endpoint_file: {}
+    webhook_basic_auth:
+      username: monitor
+      password_file: {}
+    # only the passwords of the basic auth and the proxies are read from files
+    webhook_headers:
+      password_file: /does/not/exist
+",
+        pat_path, webhook_path, password_path
+    );
+    std::fs::write(&cfg_path, &yaml).unwrap();
+    let cfg = parse_cfg(&cfg_path, false).unwrap();
+    assert_eq!(cfg.scan_api_token.as_deref(), Some("scan_secret"));
+    assert_eq!(
+        cfg.runner_sets[0].webhook_endpoint,
+        "https://example.com?token=webhook_secret"
+    );
+    match &cfg.runner_sets[0].webhook.auth {
+        Some(WebhookAuth::Basic { password, .. }) => {
+            assert_eq!(password.as_deref(), Some("password_secret"))
+        }
+        auth => panic!("expected basic auth, got {:?}", auth),
+    }
+    assert_eq!(cfg.runner_sets[0].webhook.headers[0].1, "/does/not/exist");
+    // Debug output doesn't contain any secrets
+    let debug = format!("{:?}", cfg);
+    assert!(!debug.contains("secret"));
+    assert!(debug.contains("someOrg"));
+
+    // a secret may only be given once
+    std::fs::write(
+        &cfg_path,
+        yaml.replace(
+            "github_pat_file:",
+            "github_pat: some_pat\n    github_pat_file:",
+        ),
+    )
+    .unwrap();
+    let e = parse_cfg(&cfg_path, false).unwrap_err();
+    assert!(format!("{:#}", e).contains("only one of github_pat and github_pat_file may be set"));
+
+    for path in [pat_path, webhook_path, password_path, cfg_path] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,86 @@
+use crate::signature::{sign, verify};
+
+#[test]
+fn sign_and_verify_test() {
+    let body = br#"{"text": "runner-1 went offline"}"#;
+    let signature = sign("shared_secret", 1_700_000_000, body);
+    // the documented algorithm: hex(HMAC-SHA256(secret, "{timestamp}.{body}"))
+    assert!(signature.starts_with("sha256="));
+    assert_eq!(signature.len(), "sha256=".len() + 64);
+    assert_eq!(signature, sign("shared_secret", 1_700_000_000, body));
+    // computed independently with python's hmac module
+    assert_eq!(
+        signature,
+        "sha256=3a0a1723bab3f1bc1ca4d151f2f6ad8f3c74825d0cd2758350b32b433aeb6d52"
+    );
+
+    verify(
+        "shared_secret",
+        1_700_000_000,
+        &signature,
+        body,
+        1_700_000_100,
+        300,
+    )
+    .unwrap();
+
+    let e = verify(
+        "other_secret",
+        1_700_000_000,
+        &signature,
+        body,
+        1_700_000_100,
+        300,
+    );
+    assert_eq!(e.unwrap_err().to_string(), "the signature doesn't match");
+    let e = verify(
+        "shared_secret",
+        1_700_000_000,
+        &signature,
+        b"{}",
+        1_700_000_100,
+        300,
+    );
+    assert_eq!(e.unwrap_err().to_string(), "the signature doesn't match");
+    // the timestamp is signed as well
+    let e = verify(
+        "shared_secret",
+        1_700_000_001,
+        &signature,
+        body,
+        1_700_000_100,
+        300,
+    );
+    assert_eq!(e.unwrap_err().to_string(), "the signature doesn't match");
+    // replayed too late
+    let e = verify(
+        "shared_secret",
+        1_700_000_000,
+        &signature,
+        body,
+        1_700_001_000,
+        300,
+    );
+    assert_eq!(
+        e.unwrap_err().to_string(),
+        "the timestamp is more than 300 seconds off"
+    );
+    assert!(verify(
+        "shared_secret",
+        1_700_000_000,
+        "md5=00",
+        body,
+        1_700_000_100,
+        300
+    )
+    .is_err());
+    assert!(verify(
+        "shared_secret",
+        1_700_000_000,
+        "sha256=zz",
+        body,
+        1_700_000_100,
+        300
+    )
+    .is_err());
+}
@@ -0,0 +1,139 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::scan::Scanner;
+use crate::status_api::{start_status_api, StatusApiState};
+use crate::structs::{
+    Config, DeliveredAlert, MonitorState, RunnerMap, RunnerSetConfig, RunnerStateChange,
+};
+use crate::test_fixtures::{test_cfg_with_templates, test_runner, test_runner_set};
+use crate::{alert::AlertHandler, test_alert_handler::TestAlertHandler};
+use crate::{alert::RecordingAlertHandler, github::key_runner};
+
+const RUNNER_SET: &str = "org: chrisTestOrg; github: https://github.com/api/v3";
+
+#[tokio::test]
+async fn status_api_test() {
+    let cfg = Arc::new(Config {
+        runner_sets: vec![RunnerSetConfig {
+            name: String::from(RUNNER_SET),
+            github_endpoint: String::from(
+                "https://github.com/api/v3/orgs/chrisTestOrg/actions/runners",
+            ),
+            webhook_endpoint: String::from("https://example.com?token=secret"),
+            ..test_runner_set("test-org")
+        }],
+        github_timeout: Duration::from_millis(30),
+        inbound_timeout: Duration::from_millis(30),
+        allow_http: true,
+        status_api_address: Some("127.0.0.1:9001".parse().unwrap()),
+        ..test_cfg_with_templates()
+    });
+    let runners = RunnerMap::from([
+        key_runner(test_runner(RUNNER_SET, 69, true)),
+        key_runner(test_runner(RUNNER_SET, 420, false)),
+    ]);
+    let mut state = MonitorState::new(&cfg, runners);
+
+    // pretend an alert got sent
+    let change = RunnerStateChange::Offline(
+        test_runner(RUNNER_SET, 420, true),
+        test_runner(RUNNER_SET, 420, false),
+    );
+    let mut alert_handler =
+        RecordingAlertHandler::new(TestAlertHandler::new([change.clone()].into()));
+    alert_handler.send_alert(&cfg, change).await.unwrap();
+    state.record_alerts(&alert_handler.delivered);
+
+    let state = Arc::new(RwLock::new(state));
+    start_status_api(
+        cfg.status_api_address.unwrap(),
+        StatusApiState {
+            scanner: Arc::new(Scanner::new(cfg.clone(), state).unwrap()),
+        },
+    )
+    .await
+    .unwrap();
+
+    let base = "http://127.0.0.1:9001";
+    let client = Client::new();
+
+    let healthz: Value = client
+        .get(format!("{}/healthz", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(healthz, json!({ "status": "ok" }));
+
+    let sets: Value = client
+        .get(format!("{}/sets", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(sets[0]["runner_count"], json!(2));
+    assert!(sets[0]["last_scan_time"].is_string());
+    assert_eq!(sets[0]["last_alert"]["kind"], json!("offline"));
+    assert_eq!(sets[0]["last_alert"]["runner_id"], json!(420));
+
+    let all_runners: Value = client
+        .get(format!("{}/runners", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(all_runners.as_object().unwrap().len(), 2);
+
+    let runner: Value = client
+        .get(format!(
+            "{}/runners/org%3A%20chrisTestOrg%3B%20github%3A%20https%3A%2F%2Fgithub.com%2Fapi%2Fv3/420",
+            base
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(runner["name"], json!("runner-420"));
+    assert_eq!(runner["interpret_online"], json!(false));
+    assert_eq!(runner["online_state_change_since"], json!(0));
+    // the secret webhook endpoint must never be exposed
+    assert!(runner.get("webhook_endpoint").is_none());
+
+    let resp = client
+        .get(format!("{}/runners/unknown/420", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // no scan_api_token configured
+    let resp = client
+        .post(format!("{}/scan", base))
+        .bearer_auth("anything")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn delivered_alert_test() {
+    let alert = DeliveredAlert::new(&RunnerStateChange::Created(test_runner(
+        RUNNER_SET, 12, true,
+    )));
+    assert_eq!(alert.kind, "created");
+    assert_eq!(alert.runner_id, 12);
+    assert_eq!(alert.runner_name, "runner-12");
+}
@@ -0,0 +1,103 @@
+use axum::{body::Bytes, extract::State, http::HeaderMap, routing::post, Router};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use tracing::{info_span, Instrument};
+use tracing_subscriber::layer::SubscriberExt;
+
+use crate::alert::{AlertHandler, RecordingAlertHandler};
+use crate::inbound_alert_handler::InboundAlertHandler;
+use crate::structs::{Config, Runner, RunnerStateChange};
+use crate::telemetry::{init_telemetry, OtelConfig, OtelProtocol};
+use crate::test_fixtures::test_cfg_with_templates;
+
+#[derive(Default)]
+struct Received {
+    // path of every OTLP export
+    exports: Vec<String>,
+    traceparents: Vec<String>,
+}
+
+type ReceivedState = State<Arc<Mutex<Received>>>;
+
+// stands in for both the OTLP collector and the webhook
+async fn collector_mock(address: &str) -> Arc<Mutex<Received>> {
+    let received = Arc::new(Mutex::new(Received::default()));
+    let export = |path: &'static str| {
+        post(
+            move |State(received): ReceivedState, body: Bytes| async move {
+                assert!(!body.is_empty());
+                received.lock().await.exports.push(String::from(path));
+            },
+        )
+    };
+    let app = Router::new()
+        .route("/v1/traces", export("/v1/traces"))
+        .route("/v1/metrics", export("/v1/metrics"))
+        .route(
+            "/webhook",
+            post(
+                |State(received): ReceivedState, headers: HeaderMap| async move {
+                    if let Some(traceparent) = headers.get("traceparent") {
+                        let traceparent = traceparent.to_str().unwrap().to_string();
+                        received.lock().await.traceparents.push(traceparent);
+                    }
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = TcpListener::bind(address).await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    received
+}
+
+// the batch exporter needs a second thread to flush
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn telemetry_test() {
+    let received = collector_mock("127.0.0.1:9009").await;
+    let telemetry = init_telemetry(&OtelConfig {
+        endpoint: String::from("http://127.0.0.1:9009/"),
+        protocol: OtelProtocol::Http,
+        metrics_interval_seconds: 60,
+    })
+    .unwrap();
+    let subscriber = tracing_subscriber::registry().with(telemetry.layer());
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let cfg = Config {
+        allow_http: true,
+        ..test_cfg_with_templates()
+    };
+    let mut alert_handler = RecordingAlertHandler::new(InboundAlertHandler::new(&cfg).unwrap());
+    let change = RunnerStateChange::Created(Runner {
+        utc_ping_time: String::from("1"),
+        interpret_online: Some(true),
+        online_for_github_api: true,
+        busy: false,
+        online_state_change_since: 0,
+        runner_set: String::from("org: a"),
+        id: 1,
+        name: String::from("runner-1"),
+        os: String::from("linux"),
+        labels: vec![],
+        webhook_endpoint: String::from("http://127.0.0.1:9009/webhook"),
+        webhook_cloudevents: None,
+    });
+    alert_handler
+        .send_alert(&cfg, change)
+        .instrument(info_span!("scan", scan_id = 1))
+        .await
+        .unwrap();
+
+    tokio::task::spawn_blocking(move || telemetry.shutdown())
+        .await
+        .unwrap();
+
+    let received = received.lock().await;
+    // the trace is continued by the webhook
+    assert_eq!(received.traceparents.len(), 1);
+    assert!(received.traceparents[0].starts_with("00-"));
+    assert!(received.exports.contains(&String::from("/v1/traces")));
+    // the alert counter
+    assert!(received.exports.contains(&String::from("/v1/metrics")));
+}
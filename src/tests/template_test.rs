@@ -0,0 +1,482 @@
+use chrono::Duration;
+use std::path::Path;
+use tera::Tera;
+
+use crate::check::{sample_report, validate_cfg};
+use crate::config::parse_cfg;
+use crate::github::{get_github_settings_url, runner_key};
+use crate::inbound_alert_handler::InboundAlertHandler;
+use crate::structs::{
+    Config, Runner, RunnerSetConfig, RunnerSetKind, RunnerStateChange, WebhookOptions,
+};
+use crate::template::{register_filters, TemplateFormat};
+use crate::test_fixtures::{
+    temp_dir, test_cfg, test_cfg_with_templates, test_offline_change, test_runner, test_runner_set,
+};
+
+fn render(template: &str, ctx: &tera::Context) -> String {
+    let mut tera = Tera::default();
+    register_filters(&mut tera);
+    tera.add_raw_template("test", template).unwrap();
+    tera.render("test", ctx).unwrap()
+}
+
+#[test]
+fn filters_test() {
+    let mut ctx = tera::Context::new();
+    ctx.insert("text", "say \"hi\"\n<@here> & *bold* [link](x)");
+    assert_eq!(
+        render("{{ text | json_string }}", &ctx),
+        r#"say \"hi\"\n<@here> & *bold* [link](x)"#
+    );
+    assert_eq!(
+        render("{{ text | slack_escape }}", &ctx),
+        "say \"hi\"\n&lt;@here&gt; &amp; *bold* [link](x)"
+    );
+    assert_eq!(
+        render("{{ text | markdown_escape }}", &ctx),
+        "say \"hi\"\n\\<@here\\> & \\*bold\\* \\[link\\]\\(x\\)"
+    );
+    for (seconds, expected) in [
+        (42, "42s"),
+        (125, "2m 5s"),
+        (3 * 3600 + 25 * 60, "3h 25m"),
+        (2 * 86400 + 3 * 3600 + 59, "2d 3h"),
+        (-90, "-1m 30s"),
+    ] {
+        ctx.insert("seconds", &seconds);
+        assert_eq!(render("{{ seconds | humanize_duration }}", &ctx), expected);
+    }
+}
+
+#[test]
+fn filters_template_test() {
+    let cfg = Config {
+        offline_template_path: Some(String::from("src/tests/test_filters_template.txt.j2")),
+        ..test_cfg_with_templates()
+    };
+    let alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    let change = test_offline_change(Runner {
+        name: String::from("runner_1 <@here> \"quoted\""),
+        ..test_runner(RUNNER_SET, 7, false)
+    });
+    let body = alert_handler.render_alert(&cfg, &change).unwrap().body;
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        body[0]["summary"],
+        "Runner went Offline: runner_1 <@here> \"quoted\""
+    );
+    assert_eq!(body[0]["markdown"], "runner\\_1 \\<@here\\> \"quoted\"");
+    assert_eq!(body[0]["slack"], "runner_1 &lt;@here&gt; \"quoted\"");
+    assert_eq!(body[0]["offline_for"], "unknown");
+}
+
+#[test]
+fn settings_url_test() {
+    assert_eq!(
+        get_github_settings_url("https://api.github.com", RunnerSetKind::Org, "my-org"),
+        "https://github.com/organizations/my-org/settings/actions/runners"
+    );
+    assert_eq!(
+        get_github_settings_url(
+            "https://github.example.com/api/v3/",
+            RunnerSetKind::Repo,
+            "my-org/my-repo"
+        ),
+        "https://github.example.com/my-org/my-repo/settings/actions/runners"
+    );
+    assert_eq!(
+        get_github_settings_url(
+            "https://github.example.com/api/v3",
+            RunnerSetKind::Enterprise,
+            "my-enterprise"
+        ),
+        "https://github.example.com/enterprises/my-enterprise/settings/actions/runners"
+    );
+}
+
+fn write_template(dir: &Path, name: &str, content: &str) {
+    let path = dir.join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+const RUNNER_SET: &str = "org: my-org; github: https://api.github.com";
+
+// the four template paths are only set when with_paths is true
+fn get_test_cfg(template_dir: Option<&Path>, with_paths: bool) -> Config {
+    Config {
+        runner_sets: vec![RunnerSetConfig {
+            webhook: WebhookOptions {
+                headers: vec![(
+                    String::from("X-Title"),
+                    String::from("{{ new_runner.name | markdown_escape }} is {{ kind }}"),
+                )],
+                ..WebhookOptions::default()
+            },
+            ..test_runner_set("my-org")
+        }],
+        template_dir: template_dir.map(|dir| dir.to_str().unwrap().to_string()),
+        ..if with_paths {
+            test_cfg_with_templates()
+        } else {
+            test_cfg()
+        }
+    }
+}
+
+fn render_alert(
+    alert_handler: &InboundAlertHandler,
+    cfg: &Config,
+    runner_set: &str,
+    online: bool,
+) -> String {
+    let change = if online {
+        RunnerStateChange::Online(
+            test_runner(runner_set, 7, false),
+            test_runner(runner_set, 7, true),
+        )
+    } else {
+        test_offline_change(test_runner(runner_set, 7, false))
+    };
+    alert_handler.render_alert(cfg, &change).unwrap().body
+}
+
+#[test]
+fn template_dir_test() {
+    let dir = temp_dir("template_dir");
+    write_template(
+        &dir,
+        "base.j2",
+        "{% macro name(runner) %}*{{ runner.name }}*{% endmacro name %}\
+         {% block text %}{% endblock text %}",
+    );
+    write_template(
+        &dir,
+        "online.j2",
+        "{% extends \"base.j2\" %}{% import \"base.j2\" as m %}\
+         {% block text %}{{ m::name(runner=new_runner) }} is back{% endblock text %}",
+    );
+    write_template(&dir, "footer.j2", " ({{ runner_set.name }})");
+    write_template(
+        &dir,
+        "offline.j2",
+        "{{ new_runner.name }} is gone{% include \"footer.j2\" %}",
+    );
+    write_template(
+        &dir,
+        "my-org/offline.j2",
+        "my-org lost {{ new_runner.name }}",
+    );
+    let cfg = get_test_cfg(Some(&dir), true);
+    let alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    // the per-set template wins
+    assert_eq!(
+        render_alert(&alert_handler, &cfg, RUNNER_SET, false),
+        "my-org lost runner-7"
+    );
+    // then `<kind>.j2`, which extends base.j2
+    assert_eq!(
+        render_alert(&alert_handler, &cfg, RUNNER_SET, true),
+        "*runner-7* is back"
+    );
+    // then the configured path
+    let created = RunnerStateChange::Created(test_runner(RUNNER_SET, 7, true));
+    let body = alert_handler.render_alert(&cfg, &created).unwrap().body;
+    assert!(body.contains("Created new Runner: runner-7"), "{}", body);
+    // the format of the runner set wins over both, but not over its own directory
+    let mut format_cfg = get_test_cfg(Some(&dir), true);
+    format_cfg.runner_sets[0].format = Some(TemplateFormat::PlainText);
+    let alert_handler = InboundAlertHandler::new(&format_cfg).unwrap();
+    assert!(render_alert(&alert_handler, &format_cfg, RUNNER_SET, true)
+        .starts_with("Runner runner-7 is back online"));
+    assert_eq!(
+        render_alert(&alert_handler, &format_cfg, RUNNER_SET, false),
+        "my-org lost runner-7"
+    );
+
+    // without the configured paths every kind is needed in the template_dir
+    let cfg = get_test_cfg(Some(&dir), false);
+    assert_eq!(
+        InboundAlertHandler::new(&cfg).err().unwrap().to_string(),
+        format!(
+            "No created template found for {}; set format or created_template_path, or add created.j2 to the template_dir",
+            RUNNER_SET
+        )
+    );
+    write_template(&dir, "created.j2", "created");
+    write_template(&dir, "removed.j2", "removed");
+    let alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    let body = alert_handler.render_alert(&cfg, &created).unwrap().body;
+    assert_eq!(body, "created");
+    // runner sets that are no longer configured only get `<kind>.j2`
+    assert_eq!(
+        render_alert(&alert_handler, &cfg, "org: gone", false),
+        "runner-7 is gone (org: gone)"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn alert_context_test() {
+    let dir = temp_dir("alert_context");
+    write_template(
+        &dir,
+        "my-org/online.j2",
+        "{{ kind }}|{{ runner_set.kind }}|{{ runner_set.slug }}|{{ runner_set.settings_url }}|\
+         {{ duration_offline | humanize_duration }}|{{ monitor.version }}|{{ scan_time }}",
+    );
+    let cfg = get_test_cfg(Some(&dir), true);
+    let mut alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    alert_handler.state_since.insert(
+        runner_key(RUNNER_SET, 7),
+        alert_handler.scan_time - Duration::seconds(3 * 3600 + 25 * 60),
+    );
+
+    let request = alert_handler
+        .render_alert(
+            &cfg,
+            &RunnerStateChange::Online(
+                test_runner(RUNNER_SET, 7, false),
+                test_runner(RUNNER_SET, 7, true),
+            ),
+        )
+        .unwrap();
+    assert_eq!(
+        request.body,
+        format!(
+            "online|org|my-org|https://github.com/organizations/my-org/settings/actions/runners|\
+             3h 25m|{}|{}",
+            env!("CARGO_PKG_VERSION"),
+            alert_handler.scan_time.to_rfc3339()
+        )
+    );
+    assert_eq!(
+        request.webhook.headers,
+        vec![(
+            String::from("X-Title"),
+            String::from("runner\\-7 is online")
+        )]
+    );
+}
+
+#[test]
+fn format_cfg_test() {
+    let cfg_path = temp_dir("format_cfg").join("cfg.yaml");
+    std::fs::write(
+        &cfg_path,
+        "grace_period: 0
+format: slack
+orgs:
+  - name: someOrg
+    github_base_uri: https://api.github.com
+    github_pat: some_pat
+    webhook_endpoint: https://example.com/event
+  - name: otherOrg
+    github_base_uri: https://api.github.com
+    github_pat: some_pat
+    webhook_endpoint: https://example.com/event
+    format: plain-text
+  - name: customOrg
+    github_base_uri: https://api.github.com
+    github_pat: some_pat
+    webhook_endpoint: https://example.com/event
+    webhook_content_type: application/x-slack
+",
+    )
+    .unwrap();
+    let cfg = parse_cfg(cfg_path.to_str().unwrap(), false).unwrap();
+    std::fs::remove_dir_all(cfg_path.parent().unwrap()).unwrap();
+    let formats: Vec<_> = cfg
+        .runner_sets
+        .iter()
+        .map(|s| (s.format, s.webhook.content_type.as_deref()))
+        .collect();
+    assert_eq!(
+        formats,
+        // the global format is only used by the templates and the content type
+        vec![
+            (None, Some("application/json")),
+            (
+                Some(TemplateFormat::PlainText),
+                Some("text/plain; charset=utf-8")
+            ),
+            (None, Some("application/x-slack")),
+        ]
+    );
+    // the config is complete without any template
+    assert_eq!(validate_cfg(&cfg), Vec::<String>::new());
+}
+
+fn render_builtin(format: TemplateFormat, change: Option<RunnerStateChange>) -> String {
+    let mut cfg = get_test_cfg(None, false);
+    cfg.format = Some(format);
+    cfg.runner_sets[0].format = Some(format);
+    // the header needs a new_runner
+    cfg.runner_sets[0].webhook.headers.clear();
+    let mut alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    alert_handler.state_since.insert(
+        runner_key(RUNNER_SET, 42),
+        alert_handler.scan_time - Duration::seconds(125),
+    );
+    match change {
+        Some(change) => alert_handler.render_alert(&cfg, &change),
+        None => alert_handler.render_report(&cfg.runner_sets[0], &sample_report(RUNNER_SET)),
+    }
+    .unwrap()
+    .body
+}
+
+// every kind of alert and the report, the first one being the offline alert
+fn render_all(format: TemplateFormat) -> Vec<String> {
+    let (online, offline) = (
+        test_runner(RUNNER_SET, 42, true),
+        test_runner(RUNNER_SET, 42, false),
+    );
+    [
+        Some(RunnerStateChange::Offline(online.clone(), offline.clone())),
+        Some(RunnerStateChange::Online(offline.clone(), online.clone())),
+        Some(RunnerStateChange::Created(online)),
+        Some(RunnerStateChange::Removed(offline)),
+        None,
+    ]
+    .into_iter()
+    .map(|change| render_builtin(format, change))
+    .collect()
+}
+
+fn render_all_json(format: TemplateFormat) -> Vec<serde_json::Value> {
+    render_all(format)
+        .iter()
+        .map(|body| {
+            serde_json::from_str(body).unwrap_or_else(|e| {
+                panic!("{} is not valid JSON: {}\n{}", format.as_str(), e, body)
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn builtin_generic_json_test() {
+    let bodies = render_all_json(TemplateFormat::GenericJson);
+    let offline = &bodies[0];
+    assert_eq!(offline["kind"], "offline");
+    assert_eq!(offline["title"], "Runner runner-42 went offline");
+    assert_eq!(offline["new_runner"]["name"], "runner-42");
+    assert_eq!(offline["old_runner"]["interpret_online"], true);
+    assert_eq!(offline["runner_set"]["slug"], "my-org");
+    assert_eq!(offline["monitor"]["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(bodies[1]["duration_offline"], 125);
+    assert_eq!(bodies[2]["old_runner"], serde_json::Value::Null);
+    assert_eq!(bodies[3]["title"], "Runner runner-42 was removed");
+    assert_eq!(bodies[4]["kind"], "report");
+    assert_eq!(bodies[4]["report"]["runner_count"], 1);
+    assert!(bodies[4]["text"]
+        .as_str()
+        .unwrap()
+        .contains("Outage: sample-runner for 1h 0m"));
+}
+
+#[test]
+fn builtin_slack_test() {
+    let bodies = render_all_json(TemplateFormat::Slack);
+    assert_eq!(bodies[0]["text"], "Runner runner-42 went offline");
+    assert_eq!(bodies[0]["attachments"][0]["color"], "#E01E5A");
+    assert_eq!(bodies[1]["attachments"][0]["color"], "#2EB886");
+    assert!(bodies[1]["attachments"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("Offline for 2m 5s"));
+    assert_eq!(
+        bodies[4]["text"],
+        format!("Runner report for {}: 1 of 1 runners offline", RUNNER_SET)
+    );
+}
+
+#[test]
+fn builtin_teams_test() {
+    let bodies = render_all_json(TemplateFormat::Teams);
+    let card = &bodies[0]["attachments"][0]["content"];
+    assert_eq!(card["type"], "AdaptiveCard");
+    assert_eq!(card["body"][0]["text"], "Runner runner-42 went offline");
+    assert_eq!(card["body"][0]["color"], "Attention");
+    assert!(card["body"][1]["text"]
+        .as_str()
+        .unwrap()
+        .contains("OS: linux\n\nLabels: self-hosted, linux"));
+    assert_eq!(
+        card["actions"][0]["url"],
+        "https://github.com/organizations/my-org/settings/actions/runners"
+    );
+}
+
+#[test]
+fn builtin_discord_test() {
+    let bodies = render_all_json(TemplateFormat::Discord);
+    let embed = &bodies[0]["embeds"][0];
+    assert_eq!(embed["title"], "Runner runner\\-42 went offline");
+    assert_eq!(embed["color"], 0xE01E5A);
+    assert_eq!(
+        embed["url"],
+        "https://github.com/organizations/my-org/settings/actions/runners"
+    );
+    // nobody gets pinged and the text can't be formatted by runner names
+    assert_eq!(
+        bodies[0]["allowed_mentions"]["parse"],
+        serde_json::json!([])
+    );
+    // only the names are escaped, the settings URL stays a link
+    let description = embed["description"].as_str().unwrap();
+    assert!(
+        description.contains("Runner set: org: my\\-org; github: https://api\\.github\\.com"),
+        "{}",
+        description
+    );
+    assert!(
+        description.contains("Settings: https://github.com/organizations/my-org/settings"),
+        "{}",
+        description
+    );
+    assert_eq!(bodies[2]["embeds"][0]["color"], 0x439FE0);
+}
+
+#[test]
+fn builtin_mattermost_test() {
+    let bodies = render_all_json(TemplateFormat::Mattermost);
+    assert_eq!(bodies[0]["text"], "Runner runner\\-42 went offline");
+    assert_eq!(bodies[0]["attachments"][0]["color"], "#E01E5A");
+    assert_eq!(bodies[3]["attachments"][0]["color"], "#808080");
+}
+
+#[test]
+fn builtin_plain_text_test() {
+    let bodies = render_all(TemplateFormat::PlainText);
+    assert_eq!(
+        bodies[1],
+        format!(
+            "Runner runner-42 is back online
+Runner set: {}
+OS: linux
+Labels: self-hosted, linux
+Offline for 2m 5s
+Settings: https://github.com/organizations/my-org/settings/actions/runners
+",
+            RUNNER_SET
+        )
+    );
+    assert_eq!(
+        bodies[4],
+        format!(
+            "Runner report for {}: 1 of 1 runners offline
+Period: 2024-05-01T00:00:00+00:00 - 2024-05-02T00:00:00+00:00
+Offline: sample-runner since 2024-05-01T00:00:00+00:00
+Outage: sample-runner for 1h 0m
+Flapping: sample-runner changed state 2 times
+Created: sample-runner
+Removed: sample-runner
+",
+            RUNNER_SET
+        )
+    );
+}
@@ -2,15 +2,21 @@ use anyhow::Result;
 use std::collections::hash_set::HashSet;
 
 use crate::alert::AlertHandler;
+use crate::event::Event;
 use crate::structs::{Config, RunnerStateChange};
 
 pub struct TestAlertHandler {
     expect_changes: HashSet<RunnerStateChange>,
+    // the events the changes were delivered with
+    pub events: Vec<Event>,
 }
 
 impl TestAlertHandler {
     pub fn new(expect_changes: HashSet<RunnerStateChange>) -> Self {
-        TestAlertHandler { expect_changes }
+        TestAlertHandler {
+            expect_changes,
+            events: vec![],
+        }
     }
     pub fn assert_all_received(&self) {
         assert!(self.expect_changes.is_empty());
@@ -18,13 +24,19 @@ impl TestAlertHandler {
 }
 
 impl AlertHandler for TestAlertHandler {
-    async fn send_alert(&mut self, _cfg: &Config, change: RunnerStateChange) -> Result<()> {
+    async fn deliver_alert(
+        &mut self,
+        _cfg: &Config,
+        change: RunnerStateChange,
+        event: &Event,
+    ) -> Result<()> {
         if !self.expect_changes.remove(&change) {
             panic!(
                 "expected one of:\n{:?}\ngot:\n{:?}",
                 self.expect_changes, change
             );
         }
+        self.events.push(event.clone());
         Ok(())
     }
 }
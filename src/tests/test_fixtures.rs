@@ -0,0 +1,117 @@
+use reqwest::Client;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::github::get_github_org_endpoint;
+use crate::structs::{
+    Config, Notifier, Runner, RunnerSetConfig, RunnerSetKind, RunnerStateChange, WebhookOptions,
+};
+
+// The configs of the tests; each test overrides what it needs with `..test_cfg()`.
+
+pub const TEST_GITHUB_BASE_URI: &str = "https://api.github.com";
+
+// an org on github.com whose alerts go to the webhook notifier
+pub fn test_runner_set(slug: &str) -> RunnerSetConfig {
+    RunnerSetConfig {
+        name: format!("org: {}; github: {}", slug, TEST_GITHUB_BASE_URI),
+        kind: RunnerSetKind::Org,
+        slug: String::from(slug),
+        github_base_uri: String::from(TEST_GITHUB_BASE_URI),
+        github_endpoint: get_github_org_endpoint(TEST_GITHUB_BASE_URI, slug),
+        webhook_endpoint: String::from("https://example.com/webhook"),
+        webhook: WebhookOptions::default(),
+        format: None,
+        notifier: Notifier::Webhook,
+        event_topic: None,
+        webhook_client: Client::new(),
+        github_client: Client::new(),
+    }
+}
+
+// no runner sets, templates or optional features
+pub fn test_cfg() -> Config {
+    Config {
+        runner_sets: vec![],
+        github_timeout: Duration::from_millis(1000),
+        inbound_timeout: Duration::from_millis(1000),
+        grace_period: 0,
+        allow_http: false,
+        dry_run: false,
+        status_api_address: None,
+        scan_api_token: None,
+        history_path: None,
+        event_sinks: vec![],
+        report_interval: None,
+        report_template_path: None,
+        template_dir: None,
+        format: None,
+        legacy_template_fields: true,
+        created_template_path: None,
+        removed_template_path: None,
+        online_template_path: None,
+        offline_template_path: None,
+    }
+}
+
+// with the alert templates in src/tests
+pub fn test_cfg_with_templates() -> Config {
+    Config {
+        created_template_path: Some(String::from("src/tests/test_created_template.txt.j2")),
+        removed_template_path: Some(String::from("src/tests/test_removed_template.txt.j2")),
+        online_template_path: Some(String::from("src/tests/test_online_template.txt.j2")),
+        offline_template_path: Some(String::from("src/tests/test_offline_template.txt.j2")),
+        ..test_cfg()
+    }
+}
+
+// an idle runner named after its id; tests override what they need with `..test_runner(...)`
+pub fn test_runner(runner_set: &str, id: i64, online: bool) -> Runner {
+    Runner {
+        utc_ping_time: String::from("2024-05-01T00:00:00+00:00"),
+        interpret_online: Some(online),
+        online_for_github_api: online,
+        busy: false,
+        online_state_change_since: 0,
+        runner_set: String::from(runner_set),
+        id,
+        name: format!("runner-{}", id),
+        os: String::from("linux"),
+        labels: vec![String::from("self-hosted"), String::from("linux")],
+        // the endpoint contains a secret that must never be exposed
+        webhook_endpoint: String::from("https://example.com/webhook?token=secret"),
+        webhook_cloudevents: None,
+    }
+}
+
+// the runner went offline since the last scan, it was online before
+pub fn test_offline_change(offline: Runner) -> RunnerStateChange {
+    let online = Runner {
+        interpret_online: Some(true),
+        online_for_github_api: true,
+        ..offline.clone()
+    };
+    RunnerStateChange::Offline(online, offline)
+}
+
+// unique per test run, so parallel runs don't share files
+pub fn temp_path(name: &str) -> String {
+    let path =
+        std::env::temp_dir().join(format!("gh_runner_uptime_{}_{}", name, std::process::id()));
+    path.to_str().unwrap().to_string()
+}
+
+// an empty directory
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(temp_path(name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// returns the path of the written config file; remove it when done
+pub fn write_temp_cfg(name: &str, yaml: &str) -> String {
+    let cfg_path = temp_path(name);
+    std::fs::write(&cfg_path, yaml).unwrap();
+    cfg_path
+}
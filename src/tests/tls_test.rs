@@ -0,0 +1,96 @@
+use axum::{http::HeaderMap, routing::get, Router};
+use reqwest::ClientBuilder;
+use tokio::net::TcpListener;
+
+use crate::tls::{build_client, TlsConfig};
+
+fn tls_config(
+    ca_cert_path: Option<&str>,
+    client_cert_path: Option<&str>,
+    client_key_path: Option<&str>,
+) -> TlsConfig {
+    TlsConfig {
+        ca_cert_path: ca_cert_path.map(String::from),
+        client_cert_path: client_cert_path.map(String::from),
+        client_key_path: client_key_path.map(String::from),
+        server_name: None,
+    }
+}
+
+#[test]
+fn tls_config_test() {
+    let url = "https://example.com/api/v3";
+    let (_, built_url) = build_client(
+        ClientBuilder::new(),
+        &tls_config(
+            Some("src/tests/test_ca.pem"),
+            Some("src/tests/test_client.pem"),
+            Some("src/tests/test_client.key"),
+        ),
+        None,
+        url,
+    )
+    .unwrap();
+    assert_eq!(built_url, url);
+
+    for (tls, expected) in [
+        (
+            tls_config(Some("src/tests/does_not_exist.pem"), None, None),
+            "Unable to read src/tests/does_not_exist.pem",
+        ),
+        (
+            tls_config(Some("src/tests/test_online_template.txt.j2"), None, None),
+            "No certificates found in src/tests/test_online_template.txt.j2",
+        ),
+        (
+            tls_config(None, Some("src/tests/test_client.pem"), None),
+            "client_cert_path and client_key_path need to be set together",
+        ),
+        (
+            tls_config(
+                None,
+                Some("src/tests/test_client.pem"),
+                Some("src/tests/test_ca.pem"),
+            ),
+            "Invalid client certificate",
+        ),
+    ] {
+        let e = build_client(ClientBuilder::new(), &tls, None, url).unwrap_err();
+        assert!(format!("{:#}", e).contains(expected), "{:#}", e);
+    }
+}
+
+#[tokio::test]
+async fn server_name_test() {
+    let app = Router::new().route(
+        "/webhook",
+        get(|headers: HeaderMap| async move { headers["host"].to_str().unwrap().to_string() }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:9011").await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let tls = TlsConfig {
+        server_name: Some(String::from("webhook.internal")),
+        ..TlsConfig::default()
+    };
+    let (client, url) = build_client(
+        ClientBuilder::new(),
+        &tls,
+        None,
+        "http://127.0.0.1:9011/webhook?token=abc",
+    )
+    .unwrap();
+    assert_eq!(url, "http://webhook.internal:9011/webhook?token=abc");
+    // the request still goes to the configured address
+    let host = client.get(&url).send().await.unwrap().text().await.unwrap();
+    assert_eq!(host, "webhook.internal:9011");
+
+    // a base URI doesn't get a trailing slash
+    let (_, url) = build_client(ClientBuilder::new(), &tls, None, "http://127.0.0.1:9011").unwrap();
+    assert_eq!(url, "http://webhook.internal:9011");
+
+    // the host is only resolved when connecting, not when the config is loaded
+    let (client, url) =
+        build_client(ClientBuilder::new(), &tls, None, "http://host.invalid:9011").unwrap();
+    assert!(client.get(&url).send().await.is_err());
+}
@@ -0,0 +1,205 @@
+use axum::{body::Bytes, extract::State, http::HeaderMap, routing::put, Router};
+use reqwest::Method;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+use crate::alert::AlertHandler;
+use crate::config::parse_cfg;
+use crate::inbound_alert_handler::InboundAlertHandler;
+use crate::signature::{verify, DEFAULT_TOLERANCE_SECONDS};
+use crate::structs::{
+    Config, Runner, RunnerSetConfig, RunnerStateChange, WebhookAuth, WebhookOptions,
+};
+use crate::test_fixtures::{test_cfg_with_templates, test_runner_set, write_temp_cfg};
+
+fn write_cfg(name: &str, runner_set: &str) -> String {
+    let yaml = format!(
+        "grace_period: 0
+created_template_path: src/tests/test_created_template.txt.j2
+removed_template_path: src/tests/test_removed_template.txt.j2
+online_template_path: src/tests/test_online_template.txt.j2
+offline_template_path: src/tests/test_offline_template.txt.j2
+orgs:
+  - name: someOrg
+    github_base_uri: https://github.example.com/api/v3
+    github_pat: some_pat
+    webhook_endpoint: https://example.com/event
+{}",
+        runner_set
+    );
+    write_temp_cfg(name, &yaml)
+}
+
+#[test]
+fn parse_webhook_options_test() {
+    std::env::set_var("GH_RUNNER_UPTIME_TEST_HEADER_TOKEN", "header_secret");
+    let cfg_path = write_cfg(
+        "webhook_cfg.yaml",
+        "    webhook_method: put
+    webhook_content_type: application/json
+    webhook_headers:
+      X-Api-Key: ${GH_RUNNER_UPTIME_TEST_HEADER_TOKEN}
+      X-Runner: \"{{ new_runner.name }}\"
+    webhook_basic_auth:
+      username: monitor
+      password: basic_secret
+    webhook_signing_secret: signing_secret
+",
+    );
+    let cfg = parse_cfg(&cfg_path, false).unwrap();
+    let webhook = &cfg.runner_sets[0].webhook;
+    assert_eq!(webhook.method, Method::PUT);
+    assert_eq!(webhook.content_type.as_deref(), Some("application/json"));
+    assert_eq!(
+        webhook.headers,
+        vec![
+            (String::from("X-Api-Key"), String::from("header_secret")),
+            (
+                String::from("X-Runner"),
+                String::from("{{ new_runner.name }}")
+            ),
+        ]
+    );
+    assert_eq!(
+        webhook.auth,
+        Some(WebhookAuth::Basic {
+            username: String::from("monitor"),
+            password: Some(String::from("basic_secret")),
+        })
+    );
+    // neither header values nor credentials show up in the Debug output
+    let debug = format!("{:?}", cfg);
+    assert!(!debug.contains("header_secret"));
+    assert!(!debug.contains("basic_secret"));
+    assert!(!debug.contains("signing_secret"));
+    std::fs::remove_file(&cfg_path).unwrap();
+
+    // the defaults
+    let cfg_path = write_cfg("webhook_default_cfg.yaml", "");
+    let cfg = parse_cfg(&cfg_path, false).unwrap();
+    assert_eq!(cfg.runner_sets[0].webhook, WebhookOptions::default());
+    std::fs::remove_file(&cfg_path).unwrap();
+
+    for (name, runner_set, expected) in [
+        (
+            "webhook_method_cfg.yaml",
+            "    webhook_method: \"NOT A METHOD\"\n",
+            "invalid webhook_method",
+        ),
+        (
+            "webhook_header_cfg.yaml",
+            "    webhook_headers:\n      \"Bad Header\": value\n",
+            "invalid webhook header name",
+        ),
+        (
+            "webhook_auth_cfg.yaml",
+            "    webhook_bearer_token: token\n    webhook_basic_auth:\n      username: monitor\n",
+            "only one of webhook_bearer_token, webhook_basic_auth and opsgenie_api_key may be set",
+        ),
+    ] {
+        let cfg_path = write_cfg(name, runner_set);
+        let e = parse_cfg(&cfg_path, false).unwrap_err();
+        assert!(format!("{:#}", e).contains(expected), "{:#}", e);
+        std::fs::remove_file(&cfg_path).unwrap();
+    }
+}
+
+#[derive(Debug)]
+struct ReceivedRequest {
+    headers: HeaderMap,
+    body: String,
+}
+
+async fn webhook_mock(address: &str) -> Arc<Mutex<Vec<ReceivedRequest>>> {
+    let received = Arc::new(Mutex::new(vec![]));
+    let app = Router::new()
+        .route(
+            "/webhook",
+            put(
+                |State(received): State<Arc<Mutex<Vec<ReceivedRequest>>>>,
+                 headers: HeaderMap,
+                 body: Bytes| async move {
+                    received.lock().await.push(ReceivedRequest {
+                        headers,
+                        body: String::from_utf8(body.to_vec()).unwrap(),
+                    });
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = TcpListener::bind(address).await.unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    received
+}
+
+#[tokio::test]
+async fn webhook_options_test() {
+    let received = webhook_mock("127.0.0.1:9010").await;
+    let runner_set = String::from("org: a; github: mock");
+    let cfg = Config {
+        runner_sets: vec![RunnerSetConfig {
+            name: runner_set.clone(),
+            github_endpoint: String::from("http://127.0.0.1:9010/github"),
+            webhook_endpoint: String::from("http://127.0.0.1:9010/webhook"),
+            webhook: WebhookOptions {
+                method: Method::PUT,
+                content_type: Some(String::from("application/json")),
+                headers: vec![
+                    (String::from("X-Static"), String::from("static")),
+                    (
+                        String::from("X-Runner"),
+                        String::from("{{ new_runner.name }}"),
+                    ),
+                ],
+                auth: Some(WebhookAuth::Bearer(String::from("bearer_secret"))),
+                signing_secret: Some(String::from("signing_secret")),
+                cloudevents: None,
+            },
+            ..test_runner_set("test-org")
+        }],
+        allow_http: true,
+        ..test_cfg_with_templates()
+    };
+    let mut alert_handler = InboundAlertHandler::new(&cfg).unwrap();
+    let change = RunnerStateChange::Created(Runner {
+        utc_ping_time: String::from("1"),
+        interpret_online: Some(true),
+        online_for_github_api: true,
+        busy: false,
+        online_state_change_since: 0,
+        runner_set,
+        id: 1,
+        name: String::from("runner-1"),
+        os: String::from("linux"),
+        labels: vec![],
+        webhook_endpoint: String::from("http://127.0.0.1:9010/webhook"),
+        webhook_cloudevents: None,
+    });
+    alert_handler.send_alert(&cfg, change).await.unwrap();
+
+    let received = received.lock().await;
+    assert_eq!(received.len(), 1);
+    let headers = &received[0].headers;
+    assert_eq!(headers["content-type"], "application/json");
+    assert_eq!(headers["x-static"], "static");
+    // header values are rendered with the alert context
+    assert_eq!(headers["x-runner"], "runner-1");
+    assert_eq!(headers["authorization"], "Bearer bearer_secret");
+    assert!(!received[0].body.is_empty());
+    // the receiver is able to verify the request
+    let timestamp: i64 = headers["x-signature-timestamp"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    verify(
+        "signing_secret",
+        timestamp,
+        headers["x-signature-256"].to_str().unwrap(),
+        received[0].body.as_bytes(),
+        chrono::Utc::now().timestamp(),
+        DEFAULT_TOLERANCE_SECONDS,
+    )
+    .unwrap();
+}
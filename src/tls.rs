@@ -0,0 +1,112 @@
+use anyhow::{bail, ensure, Context, Result};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    Certificate, Client, ClientBuilder, Identity, Url,
+};
+use serde::Deserialize;
+use std::{net::SocketAddr, sync::Arc};
+
+use crate::proxy::{apply_proxy, ProxyConfig};
+
+// the TLS settings of a GitHub or webhook endpoint
+#[derive(Debug, Deserialize, PartialEq, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    // PEM bundle of CAs that are trusted in addition to the system roots
+    pub ca_cert_path: Option<String>,
+    // PEM client certificate and PKCS#8 key for mTLS; both or none need to be set
+    pub client_cert_path: Option<String>,
+    pub client_key_path: Option<String>,
+    // Connect to the host of the URL, but use this name for SNI, the certificate check and
+    // the Host header. The host of the URL is resolved for every new connection.
+    pub server_name: Option<String>,
+}
+
+// Resolves server_name to the addresses of the host the URL had before it was rewritten.
+// All other names, e.g. of a proxy, are resolved as they are.
+struct ServerNameResolver {
+    server_name: String,
+    host: String,
+}
+
+impl Resolve for ServerNameResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = match name.as_str() == self.server_name {
+            true => self.host.clone(),
+            false => name.as_str().to_string(),
+        };
+        Box::pin(async move {
+            // port 0 is replaced with the port of the URL
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn read_pem(path: &str) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("Unable to read {}", path))
+}
+
+// Returns the client and the URL to use with it.
+// The URL only changes when server_name is set.
+pub fn build_client(
+    builder: ClientBuilder,
+    tls: &TlsConfig,
+    proxy: Option<&ProxyConfig>,
+    url: &str,
+) -> Result<(Client, String)> {
+    let mut builder = apply_proxy(builder, proxy)?;
+    if let Some(ca_cert_path) = &tls.ca_cert_path {
+        let certs = Certificate::from_pem_bundle(&read_pem(ca_cert_path)?)
+            .with_context(|| format!("Invalid CA bundle {}", ca_cert_path))?;
+        ensure!(
+            !certs.is_empty(),
+            "No certificates found in {}",
+            ca_cert_path
+        );
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    match (&tls.client_cert_path, &tls.client_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let identity = Identity::from_pkcs8_pem(&read_pem(cert_path)?, &read_pem(key_path)?)
+                .with_context(|| {
+                    format!(
+                        "Invalid client certificate {} or key {}",
+                        cert_path, key_path
+                    )
+                })?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => (),
+        _ => bail!("client_cert_path and client_key_path need to be set together"),
+    }
+    let mut url = url.to_string();
+    if let Some(server_name) = &tls.server_name {
+        // the url itself isn't part of the errors, it may contain a secret
+        let mut parsed = Url::parse(&url).context("Invalid URL for server_name")?;
+        let host = parsed
+            .host_str()
+            .context("server_name needs a URL with a host")?
+            // IPv6 addresses are in brackets
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        builder = builder.dns_resolver(Arc::new(ServerNameResolver {
+            server_name: server_name.clone(),
+            host,
+        }));
+        parsed
+            .set_host(Some(server_name))
+            .with_context(|| format!("Invalid server_name {}", server_name))?;
+        let rewritten = parsed.to_string();
+        // a base URI without a path must not get a trailing slash
+        url = match rewritten.strip_suffix('/') {
+            Some(stripped) if !url.ends_with('/') => stripped.to_string(),
+            _ => rewritten,
+        };
+    }
+    Ok((builder.build()?, url))
+}